@@ -1,6 +1,19 @@
 use crate::{
+    discovery::{dns::DnsSourceConfig, router::RouterProtocol, DiscoveryPolicy},
     dns_providers::DnsRecordType,
-    providers::{cloudflare::CloudFlareBackendConfig, digitalocean::DigitalOceanBackendConfig},
+    providers::{
+        adguard_home::AdGuardHomeBackendConfig, azure_dns::AzureDnsBackendConfig,
+        bunny::BunnyBackendConfig, cloudflare::CloudFlareBackendConfig, desec::DesecBackendConfig,
+        digitalocean::DigitalOceanBackendConfig, dyndns2::Dyndns2BackendConfig,
+        dynv6::Dynv6BackendConfig, gandi::GandiBackendConfig, gcloud_dns::GcloudDnsBackendConfig,
+        hetzner::HetznerBackendConfig, hostsfile::HostsFileBackendConfig,
+        linode::LinodeBackendConfig, namecheap::NamecheapBackendConfig,
+        netlify::NetlifyBackendConfig, ovh::OvhBackendConfig, pihole::PiholeBackendConfig,
+        porkbun::PorkbunBackendConfig, powerdns::PowerDnsBackendConfig,
+        rfc2136::Rfc2136BackendConfig, route53::Route53BackendConfig,
+        scaleway::ScalewayBackendConfig, technitium::TechnitiumBackendConfig,
+        vercel::VercelBackendConfig, vultr::VultrBackendConfig, zonefile::ZoneFileBackendConfig,
+    },
 };
 use default_net::Interface;
 use serde_derive::{Deserialize, Serialize};
@@ -16,6 +29,24 @@ pub struct Config {
 #[derive(Serialize, Deserialize)]
 pub struct Settings {
     pub external_ipv4_check_url: Option<String>,
+    /// More IPv4 discovery services, queried after `external_ipv4_check_url`
+    pub external_ipv4_check_urls: Option<Vec<String>>,
+    /// Queried over IPv6 only, for AAAA records on the `external` interface
+    pub external_ipv6_check_url: Option<String>,
+    pub external_ipv6_check_urls: Option<Vec<String>>,
+    /// STUN servers (`host` or `host:port`) queried after the HTTP services, for both families
+    pub stun_servers: Option<Vec<String>>,
+    /// Resolvers answering with our address, queried after the STUN servers
+    pub dns_sources: Option<Vec<DnsSourceConfig>>,
+    /// Protocols tried in order to ask the gateway for its WAN address, which fills the
+    /// `router_wan` interface
+    pub router_protocols: Option<Vec<RouterProtocol>>,
+    /// The default gateway if unset
+    pub router_gateway: Option<String>,
+    /// How answers from several discovery services are combined, first-success by default
+    pub discovery_policy: Option<DiscoveryPolicy>,
+    /// Timeout for each discovery service in seconds
+    pub discovery_timeout: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -23,6 +54,30 @@ pub struct DomainConfig {
     pub name: String,
     pub digital_ocean_backend: Option<DigitalOceanBackendConfig>,
     pub cloudflare_backend: Option<CloudFlareBackendConfig>,
+    pub rfc2136_backend: Option<Rfc2136BackendConfig>,
+    pub route53_backend: Option<Route53BackendConfig>,
+    pub hetzner_backend: Option<HetznerBackendConfig>,
+    pub powerdns_backend: Option<PowerDnsBackendConfig>,
+    pub dyndns2_backend: Option<Dyndns2BackendConfig>,
+    pub zonefile_backend: Option<ZoneFileBackendConfig>,
+    pub hostsfile_backend: Option<HostsFileBackendConfig>,
+    pub gandi_backend: Option<GandiBackendConfig>,
+    pub porkbun_backend: Option<PorkbunBackendConfig>,
+    pub namecheap_backend: Option<NamecheapBackendConfig>,
+    pub linode_backend: Option<LinodeBackendConfig>,
+    pub vultr_backend: Option<VultrBackendConfig>,
+    pub desec_backend: Option<DesecBackendConfig>,
+    pub dynv6_backend: Option<Dynv6BackendConfig>,
+    pub gcloud_dns_backend: Option<GcloudDnsBackendConfig>,
+    pub azure_dns_backend: Option<AzureDnsBackendConfig>,
+    pub ovh_backend: Option<OvhBackendConfig>,
+    pub scaleway_backend: Option<ScalewayBackendConfig>,
+    pub technitium_backend: Option<TechnitiumBackendConfig>,
+    pub pihole_backend: Option<PiholeBackendConfig>,
+    pub adguard_home_backend: Option<AdGuardHomeBackendConfig>,
+    pub bunny_backend: Option<BunnyBackendConfig>,
+    pub netlify_backend: Option<NetlifyBackendConfig>,
+    pub vercel_backend: Option<VercelBackendConfig>,
     pub records: Vec<Record>,
 }
 
@@ -31,6 +86,30 @@ pub struct ParsedDomainConfig {
     pub name: String,
     pub digital_ocean_backend: Option<DigitalOceanBackendConfig>,
     pub cloudflare_backend: Option<CloudFlareBackendConfig>,
+    pub rfc2136_backend: Option<Rfc2136BackendConfig>,
+    pub route53_backend: Option<Route53BackendConfig>,
+    pub hetzner_backend: Option<HetznerBackendConfig>,
+    pub powerdns_backend: Option<PowerDnsBackendConfig>,
+    pub dyndns2_backend: Option<Dyndns2BackendConfig>,
+    pub zonefile_backend: Option<ZoneFileBackendConfig>,
+    pub hostsfile_backend: Option<HostsFileBackendConfig>,
+    pub gandi_backend: Option<GandiBackendConfig>,
+    pub porkbun_backend: Option<PorkbunBackendConfig>,
+    pub namecheap_backend: Option<NamecheapBackendConfig>,
+    pub linode_backend: Option<LinodeBackendConfig>,
+    pub vultr_backend: Option<VultrBackendConfig>,
+    pub desec_backend: Option<DesecBackendConfig>,
+    pub dynv6_backend: Option<Dynv6BackendConfig>,
+    pub gcloud_dns_backend: Option<GcloudDnsBackendConfig>,
+    pub azure_dns_backend: Option<AzureDnsBackendConfig>,
+    pub ovh_backend: Option<OvhBackendConfig>,
+    pub scaleway_backend: Option<ScalewayBackendConfig>,
+    pub technitium_backend: Option<TechnitiumBackendConfig>,
+    pub pihole_backend: Option<PiholeBackendConfig>,
+    pub adguard_home_backend: Option<AdGuardHomeBackendConfig>,
+    pub bunny_backend: Option<BunnyBackendConfig>,
+    pub netlify_backend: Option<NetlifyBackendConfig>,
+    pub vercel_backend: Option<VercelBackendConfig>,
     pub records: Vec<ParsedRecord>,
 }
 
@@ -55,6 +134,30 @@ impl DomainConfig {
             name: self.name.clone(),
             digital_ocean_backend: self.digital_ocean_backend.clone(),
             cloudflare_backend: self.cloudflare_backend.clone(),
+            rfc2136_backend: self.rfc2136_backend.clone(),
+            route53_backend: self.route53_backend.clone(),
+            hetzner_backend: self.hetzner_backend.clone(),
+            powerdns_backend: self.powerdns_backend.clone(),
+            dyndns2_backend: self.dyndns2_backend.clone(),
+            zonefile_backend: self.zonefile_backend.clone(),
+            hostsfile_backend: self.hostsfile_backend.clone(),
+            gandi_backend: self.gandi_backend.clone(),
+            porkbun_backend: self.porkbun_backend.clone(),
+            namecheap_backend: self.namecheap_backend.clone(),
+            linode_backend: self.linode_backend.clone(),
+            vultr_backend: self.vultr_backend.clone(),
+            desec_backend: self.desec_backend.clone(),
+            dynv6_backend: self.dynv6_backend.clone(),
+            gcloud_dns_backend: self.gcloud_dns_backend.clone(),
+            azure_dns_backend: self.azure_dns_backend.clone(),
+            ovh_backend: self.ovh_backend.clone(),
+            scaleway_backend: self.scaleway_backend.clone(),
+            technitium_backend: self.technitium_backend.clone(),
+            pihole_backend: self.pihole_backend.clone(),
+            adguard_home_backend: self.adguard_home_backend.clone(),
+            bunny_backend: self.bunny_backend.clone(),
+            netlify_backend: self.netlify_backend.clone(),
+            vercel_backend: self.vercel_backend.clone(),
             records: parsed_records,
         }
     }
@@ -77,6 +180,7 @@ pub struct ParsedRecord {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::discovery::dns::DnsQueryClass;
     #[test]
     fn test_settings_deserialize() {
         let config: Settings = toml::from_str(
@@ -89,6 +193,80 @@ mod tests {
         assert_eq!(config.external_ipv4_check_url, Some("test_v4".to_string()));
     }
 
+    #[test]
+    fn test_ipv6_check_url_deserialize() {
+        let config: Settings = toml::from_str(
+            r#"
+        external_ipv6_check_url = "test_v6"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.external_ipv6_check_url, Some("test_v6".to_string()));
+    }
+
+    #[test]
+    fn test_discovery_policy_deserialize() {
+        let config: Settings = toml::from_str(
+            r#"
+        external_ipv4_check_urls = ["a", "b"]
+        discovery_policy = "all-agree"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.external_ipv4_check_urls.unwrap().len(), 2);
+        assert_eq!(config.discovery_policy, Some(DiscoveryPolicy::AllAgree));
+    }
+
+    #[test]
+    fn test_stun_servers_deserialize() {
+        let config: Settings = toml::from_str(
+            r#"
+        stun_servers = ["stun.l.google.com:19302"]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.stun_servers.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_dns_sources_deserialize() {
+        let config: Settings = toml::from_str(
+            r#"
+            [[dns_sources]]
+            resolver = "1.1.1.1"
+            name = "whoami.cloudflare"
+            record_type = "TXT"
+            class = "CH"
+        "#,
+        )
+        .unwrap();
+
+        let dns_sources = config.dns_sources.unwrap();
+        assert_eq!(dns_sources[0].class, Some(DnsQueryClass::CH));
+    }
+
+    #[test]
+    fn test_router_protocols_deserialize() {
+        let config: Settings = toml::from_str(
+            r#"
+        router_protocols = ["pcp", "nat-pmp", "upnp"]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.router_protocols,
+            Some(vec![
+                RouterProtocol::Pcp,
+                RouterProtocol::NatPmp,
+                RouterProtocol::Upnp
+            ])
+        );
+    }
+
     #[test]
     fn test_digitalocean_settings_deserialize() {
         let config: DigitalOceanBackendConfig = toml::from_str(
@@ -101,6 +279,29 @@ mod tests {
         assert_eq!(config.api_key, "test".to_string());
     }
 
+    #[test]
+    fn test_cloudflare_settings_deserialize() {
+        let config: CloudFlareBackendConfig = toml::from_str(
+            r#"
+        api_token = "test"
+        zone = "example.com"
+        ttl = 1
+
+            [[records]]
+            name = "www"
+            proxied = true
+            tags = ["web"]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.defaults.ttl, Some(1));
+        assert_eq!(config.defaults.proxied, None);
+        let records = config.records.unwrap();
+        assert_eq!(records[0].options.proxied, Some(true));
+        assert_eq!(records[0].options.tags, Some(vec!["web".to_string()]));
+    }
+
     #[test]
     fn test_domain_record_deserialize() {
         let config: Config = toml::from_str(
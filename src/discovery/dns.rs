@@ -0,0 +1,196 @@
+use super::{parse_address, resolve_server, AddressSource, Family};
+use crate::dns_message::{
+    self, Message, CLASS_CH, CLASS_IN, RCODE_NOERROR, TYPE_A, TYPE_AAAA, TYPE_TXT,
+};
+use crate::dns_providers::DnsBackendError;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_derive::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+const DEFAULT_PORT: u16 = 53;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DnsQueryType {
+    A,
+    AAAA,
+    TXT,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum DnsQueryClass {
+    #[default]
+    IN,
+    CH,
+}
+
+/// A resolver that answers a special name with the address the query came from, e.g.
+/// `myip.opendns.com` at resolver1.opendns.com or `whoami.cloudflare` (TXT, CH) at 1.1.1.1
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DnsSourceConfig {
+    /// `host` or `host:port`, the port defaulting to 53
+    pub resolver: String,
+    pub name: String,
+    /// A or AAAA for the family being discovered by default. Sources set to A or AAAA
+    /// are only used for that family.
+    pub record_type: Option<DnsQueryType>,
+    pub class: Option<DnsQueryClass>,
+}
+
+impl DnsSourceConfig {
+    pub fn serves(&self, family: Family) -> bool {
+        match self.record_type {
+            Some(DnsQueryType::A) => family == Family::V4,
+            Some(DnsQueryType::AAAA) => family == Family::V6,
+            _ => true,
+        }
+    }
+}
+
+pub struct DnsSource {
+    config: DnsSourceConfig,
+    timeout: Duration,
+}
+
+impl DnsSource {
+    pub fn new(config: DnsSourceConfig, timeout: Duration) -> Self {
+        Self { config, timeout }
+    }
+
+    fn new_id() -> Result<u16, DnsBackendError> {
+        let mut id = [0u8; 2];
+        SystemRandom::new()
+            .fill(&mut id)
+            .map_err(|_| DnsBackendError {
+                message: "Failed to generate DNS message ID".to_string(),
+            })?;
+        Ok(u16::from_be_bytes(id))
+    }
+}
+
+impl AddressSource for DnsSource {
+    fn name(&self) -> String {
+        format!("dns:{}@{}", self.config.name, self.config.resolver)
+    }
+
+    fn discover(&self, family: Family) -> Result<IpAddr, DnsBackendError> {
+        let qtype = match (self.config.record_type, family) {
+            (Some(DnsQueryType::TXT), _) => TYPE_TXT,
+            (_, Family::V4) => TYPE_A,
+            (_, Family::V6) => TYPE_AAAA,
+        };
+        let class = match self.config.class.unwrap_or_default() {
+            DnsQueryClass::IN => CLASS_IN,
+            DnsQueryClass::CH => CLASS_CH,
+        };
+        // The resolver is reached over `family`, so it sees our address of that family
+        let resolver = resolve_server(&self.config.resolver, DEFAULT_PORT, family)?;
+        let query = Message::new_query(Self::new_id()?, &self.config.name, qtype, class);
+        let response = dns_message::exchange_udp(resolver, &query, self.timeout)
+            .and_then(|x| Message::decode(&x))
+            .map_err(|e| DnsBackendError {
+                message: format!("Query to {resolver} failed: {e}"),
+            })?;
+        if response.rcode() != RCODE_NOERROR {
+            return Err(DnsBackendError {
+                message: format!(
+                    "{resolver} answered {} with rcode {}",
+                    self.config.name,
+                    response.rcode()
+                ),
+            });
+        }
+        let answer = response
+            .answers
+            .iter()
+            .find(|x| x.rtype == qtype)
+            .and_then(|x| x.data_string())
+            .ok_or(DnsBackendError {
+                message: format!("{resolver} returned no answer for {}", self.config.name),
+            })?;
+        debug!("Got answer from {}: {}", resolver, answer);
+        parse_address(&answer, family)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns_message::ResourceRecord;
+    use std::net::UdpSocket;
+    use std::thread;
+
+    /// Answer `count` queries, with TXT CH queries getting a whoami.cloudflare style answer
+    fn resolver(count: usize) -> (String, thread::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            for _ in 0..count {
+                let mut buf = [0u8; 512];
+                let (len, from) = socket.recv_from(&mut buf).unwrap();
+                let query = Message::decode(&buf[..len]).unwrap();
+                let question = &query.questions[0];
+                let rdata = match (question.qtype, question.class) {
+                    (TYPE_A, CLASS_IN) if question.name == "myip.opendns.com" => {
+                        vec![93, 184, 216, 34]
+                    }
+                    (TYPE_TXT, CLASS_CH) => b"\x0d93.184.216.34".to_vec(),
+                    _ => {
+                        let response = query.response(3).encode().unwrap();
+                        socket.send_to(&response, from).unwrap();
+                        continue;
+                    }
+                };
+                let mut response = query.response(RCODE_NOERROR);
+                response.answers.push(ResourceRecord {
+                    name: question.name.clone(),
+                    rtype: question.qtype,
+                    class: question.class,
+                    ttl: 0,
+                    rdata,
+                });
+                socket.send_to(&response.encode().unwrap(), from).unwrap();
+            }
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_dns_source() {
+        let (address, handle) = resolver(3);
+        let timeout = Duration::from_secs(3);
+        let source = |name: &str, record_type, class| {
+            DnsSource::new(
+                DnsSourceConfig {
+                    resolver: address.clone(),
+                    name: name.to_string(),
+                    record_type,
+                    class,
+                },
+                timeout,
+            )
+        };
+        let opendns = source("myip.opendns.com", None, None);
+        assert_eq!(
+            opendns.discover(Family::V4).unwrap().to_string(),
+            "93.184.216.34"
+        );
+        let cloudflare = source(
+            "whoami.cloudflare",
+            Some(DnsQueryType::TXT),
+            Some(DnsQueryClass::CH),
+        );
+        assert_eq!(
+            cloudflare.discover(Family::V4).unwrap().to_string(),
+            "93.184.216.34"
+        );
+        let error = source("unknown.example", None, None)
+            .discover(Family::V4)
+            .unwrap_err();
+        assert!(error.message.ends_with("rcode 3"), "{}", error.message);
+        handle.join().unwrap();
+        assert!(!source("myip.opendns.com", Some(DnsQueryType::A), None)
+            .config
+            .serves(Family::V6));
+    }
+}
@@ -0,0 +1,121 @@
+use super::{parse_address, AddressSource, Family};
+use crate::dns_providers::DnsBackendError;
+use reqwest::blocking::Client;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::time::Duration;
+
+/// An ipify-style service answering with the caller's address as plain text
+pub struct HttpSource {
+    url: String,
+    timeout: Duration,
+}
+
+impl HttpSource {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        Self { url, timeout }
+    }
+
+    /// A client that can only reach the service over `family`: it connects from a local
+    /// address of that family to the host's addresses of that family, so a dual-stack
+    /// service can't answer with our address of the other family instead.
+    fn client(&self, url: &reqwest::Url, family: Family) -> Result<Client, DnsBackendError> {
+        let host = url.host_str().ok_or(DnsBackendError {
+            message: format!("Discovery URL {} has no host", self.url),
+        })?;
+        let local_address = match family {
+            Family::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            Family::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let mut builder = Client::builder()
+            .timeout(self.timeout)
+            .local_address(local_address);
+        match host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(address) if address.is_ipv4() == (family == Family::V4) => {}
+            Ok(address) => {
+                return Err(DnsBackendError {
+                    message: format!("Discovery URL {} can't be used for {family:?}", address),
+                })
+            }
+            Err(_) => {
+                let port = url.port_or_known_default().unwrap_or(443);
+                let addresses: Vec<_> = (host, port)
+                    .to_socket_addrs()
+                    .map_err(|e| DnsBackendError {
+                        message: format!("Couldn't resolve {host}: {e}"),
+                    })?
+                    .filter(|x| x.is_ipv4() == (family == Family::V4))
+                    .collect();
+                if addresses.is_empty() {
+                    return Err(DnsBackendError {
+                        message: format!("{host} has no {family:?} address"),
+                    });
+                }
+                builder = builder.resolve_to_addrs(host, &addresses);
+            }
+        }
+        builder.build().map_err(|e| DnsBackendError {
+            message: format!("Failed to setup discovery client: {e:?}"),
+        })
+    }
+}
+
+impl AddressSource for HttpSource {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    fn discover(&self, family: Family) -> Result<IpAddr, DnsBackendError> {
+        let url = reqwest::Url::parse(&self.url).map_err(|e| DnsBackendError {
+            message: format!("Invalid discovery URL {}: {e}", self.url),
+        })?;
+        let response = self
+            .client(&url, family)?
+            .get(url)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Request failed: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!("Service returned {status}"),
+            });
+        }
+        let body = response.text().map_err(|e| DnsBackendError {
+            message: format!("Couldn't read response: {e:?}"),
+        })?;
+        debug!("Got response from {}: {}", self.url, body);
+        parse_address(&body, family)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_http_source() {
+        let (url, handle) = test_http::serve(2, |request| match request.path.as_str() {
+            "/ip" => Response::new(200, "93.184.216.34\n"),
+            _ => Response::new(200, "<html>Service Unavailable</html>"),
+        });
+        let timeout = Duration::from_secs(5);
+        let source = HttpSource::new(format!("{url}/ip"), timeout);
+        assert_eq!(
+            source.discover(Family::V4).unwrap().to_string(),
+            "93.184.216.34"
+        );
+        let error = HttpSource::new(format!("{url}/down"), timeout)
+            .discover(Family::V4)
+            .unwrap_err();
+        assert!(error.message.starts_with("Response is not an IP address"));
+        // The test server is IPv4 only, so it can't be used to discover an IPv6 address
+        assert!(source.discover(Family::V6).is_err());
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,283 @@
+//! External address discovery: asks one or more sources for our public address and
+//! combines their answers according to a [`DiscoveryPolicy`].
+
+pub mod dns;
+pub mod http;
+pub mod router;
+pub mod stun;
+
+use crate::dns_providers::DnsBackendError;
+use serde_derive::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    V4,
+    V6,
+}
+
+impl Family {
+    fn matches(&self, address: &IpAddr) -> bool {
+        match self {
+            Family::V4 => address.is_ipv4(),
+            Family::V6 => address.is_ipv6(),
+        }
+    }
+}
+
+/// How the answers of several sources are combined
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiscoveryPolicy {
+    /// Query sources in order and use the first valid answer
+    #[default]
+    FirstSuccess,
+    /// Query every source and use the address reported by more than half of them
+    Majority,
+    /// Query every source and only use the address if all of them report it
+    AllAgree,
+}
+
+pub trait AddressSource {
+    /// Describes the source in log messages, e.g. its URL
+    fn name(&self) -> String;
+    fn discover(&self, family: Family) -> Result<IpAddr, DnsBackendError>;
+}
+
+/// Shared address space used by carrier-grade NAT (RFC 6598)
+fn is_shared_address(address: &Ipv4Addr) -> bool {
+    let [a, b, ..] = address.octets();
+    a == 100 && (64..128).contains(&b)
+}
+
+fn is_public_v4(address: &Ipv4Addr) -> bool {
+    !(address.is_private()
+        || address.is_loopback()
+        || address.is_link_local()
+        || address.is_unspecified()
+        || address.is_broadcast()
+        || address.is_multicast()
+        || address.is_documentation()
+        || is_shared_address(address)
+        // Reserved for future use (RFC 1112)
+        || address.octets()[0] >= 240)
+}
+
+fn is_public_v6(address: &Ipv6Addr) -> bool {
+    let first = address.segments()[0];
+    !(address.is_loopback()
+        || address.is_unspecified()
+        || address.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10)
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        // Documentation (2001:db8::/32)
+        || (first == 0x2001 && address.segments()[1] == 0x0db8)
+        || address.to_ipv4_mapped().is_some())
+}
+
+/// Parse a source's answer, rejecting anything but a single public address of `family`
+pub fn parse_address(response: &str, family: Family) -> Result<IpAddr, DnsBackendError> {
+    let address: IpAddr = response.trim().parse().map_err(|_| DnsBackendError {
+        message: format!(
+            "Response is not an IP address: {:?}",
+            response.chars().take(64).collect::<String>()
+        ),
+    })?;
+    validate_address(address, family)
+}
+
+/// Reject addresses of the wrong family and ones that can't be our public address
+pub fn validate_address(address: IpAddr, family: Family) -> Result<IpAddr, DnsBackendError> {
+    if !family.matches(&address) {
+        return Err(DnsBackendError {
+            message: format!("Expected an {family:?} address, got {address}"),
+        });
+    }
+    let public = match address {
+        IpAddr::V4(address) => is_public_v4(&address),
+        IpAddr::V6(address) => is_public_v6(&address),
+    };
+    if !public {
+        return Err(DnsBackendError {
+            message: format!("{address} is not a public address"),
+        });
+    }
+    Ok(address)
+}
+
+/// Resolve a `host` or `host:port` server address to a socket address of `family`
+fn resolve_server(
+    server: &str,
+    default_port: u16,
+    family: Family,
+) -> Result<SocketAddr, DnsBackendError> {
+    if let Ok(address) = server.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    let has_port = server
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.contains(':') && port.parse::<u16>().is_ok());
+    let with_port = if has_port {
+        server.to_string()
+    } else {
+        format!("{server}:{default_port}")
+    };
+    with_port
+        .to_socket_addrs()
+        .map_err(|e| DnsBackendError {
+            message: format!("Couldn't resolve {server}: {e}"),
+        })?
+        .find(|x| family.matches(&x.ip()))
+        .ok_or(DnsBackendError {
+            message: format!("{server} has no {family:?} address"),
+        })
+}
+
+/// Query a single source, logging how long it took and whether it failed
+fn query(source: &dyn AddressSource, family: Family) -> Result<IpAddr, DnsBackendError> {
+    let start = Instant::now();
+    let result = source.discover(family);
+    match &result {
+        Ok(address) => info!(
+            "{} reported {} in {:?}",
+            source.name(),
+            address,
+            start.elapsed()
+        ),
+        Err(e) => warn!(
+            "{} failed after {:?}: {}",
+            source.name(),
+            start.elapsed(),
+            e
+        ),
+    }
+    result
+}
+
+/// Find our public address of `family` from `sources` according to `policy`
+pub fn discover(
+    sources: &[Box<dyn AddressSource>],
+    family: Family,
+    policy: DiscoveryPolicy,
+) -> Result<IpAddr, DnsBackendError> {
+    if sources.is_empty() {
+        return Err(DnsBackendError {
+            message: format!("No {family:?} address sources configured"),
+        });
+    }
+    if policy == DiscoveryPolicy::FirstSuccess {
+        return sources
+            .iter()
+            .find_map(|x| query(x.as_ref(), family).ok())
+            .ok_or(DnsBackendError {
+                message: format!("All {family:?} address sources failed"),
+            });
+    }
+    let answers: Vec<Result<IpAddr, DnsBackendError>> =
+        sources.iter().map(|x| query(x.as_ref(), family)).collect();
+    let mut counts: Vec<(IpAddr, usize)> = Vec::new();
+    for address in answers.iter().flatten() {
+        match counts.iter_mut().find(|(x, _)| x == address) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((*address, 1)),
+        }
+    }
+    let (address, count) = counts
+        .iter()
+        .max_by_key(|(_, count)| *count)
+        .copied()
+        .ok_or(DnsBackendError {
+            message: format!("All {family:?} address sources failed"),
+        })?;
+    let agreed = match policy {
+        DiscoveryPolicy::Majority => count * 2 > sources.len(),
+        _ => count == sources.len(),
+    };
+    if !agreed {
+        return Err(DnsBackendError {
+            message: format!(
+                "{family:?} address sources disagree ({count} of {} reported {address}, policy {policy:?})",
+                sources.len()
+            ),
+        });
+    }
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedSource(Result<IpAddr, String>);
+
+    impl AddressSource for FixedSource {
+        fn name(&self) -> String {
+            "fixed".to_string()
+        }
+        fn discover(&self, _family: Family) -> Result<IpAddr, DnsBackendError> {
+            self.0
+                .clone()
+                .map_err(|message| DnsBackendError { message })
+        }
+    }
+
+    fn sources(answers: &[Option<&str>]) -> Vec<Box<dyn AddressSource>> {
+        answers
+            .iter()
+            .map(|x| {
+                Box::new(FixedSource(
+                    x.map(|x| x.parse().unwrap()).ok_or("timed out".to_string()),
+                )) as Box<dyn AddressSource>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_policies() {
+        let mixed = sources(&[None, Some("1.1.1.1"), Some("1.1.1.1"), Some("8.8.8.8")]);
+        let first = discover(&mixed, Family::V4, DiscoveryPolicy::FirstSuccess).unwrap();
+        assert_eq!(first.to_string(), "1.1.1.1");
+        // Two of four sources isn't a majority
+        assert!(discover(&mixed, Family::V4, DiscoveryPolicy::Majority).is_err());
+
+        let majority = sources(&[Some("1.1.1.1"), Some("1.1.1.1"), None]);
+        let address = discover(&majority, Family::V4, DiscoveryPolicy::Majority).unwrap();
+        assert_eq!(address.to_string(), "1.1.1.1");
+        assert!(discover(&majority, Family::V4, DiscoveryPolicy::AllAgree).is_err());
+
+        let agreeing = sources(&[Some("1.1.1.1"), Some("1.1.1.1")]);
+        assert!(discover(&agreeing, Family::V4, DiscoveryPolicy::AllAgree).is_ok());
+        assert!(discover(&sources(&[None]), Family::V4, DiscoveryPolicy::FirstSuccess).is_err());
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address(" 93.184.216.34\n", Family::V4).unwrap(),
+            "93.184.216.34".parse::<IpAddr>().unwrap()
+        );
+        assert!(parse_address("2606:4700::1111", Family::V6).is_ok());
+        for bogus in [
+            "<html><body>503 Service Unavailable</body></html>",
+            "   ",
+            "192.168.1.10",
+            "100.64.0.1",
+            "127.0.0.1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:8.8.8.8",
+        ] {
+            let family = if bogus.contains(':') {
+                Family::V6
+            } else {
+                Family::V4
+            };
+            assert!(parse_address(bogus, family).is_err(), "accepted {bogus:?}");
+        }
+        assert!(parse_address("2606:4700::1111", Family::V4).is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! Asks the local gateway for its WAN address with UPnP IGD, NAT-PMP (RFC 6886) or
+//! PCP (RFC 6887), so no third-party service is involved.
+
+use super::{is_shared_address, resolve_server, validate_address, AddressSource, Family};
+use crate::dns_providers::DnsBackendError;
+use crate::providers::xml;
+use reqwest::blocking::Client;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_derive::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const SSDP_PORT: u16 = 1900;
+const SSDP_MULTICAST: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250)), SSDP_PORT);
+const IGD_DEVICE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+/// NAT-PMP and PCP share a port, PCP servers answer NAT-PMP requests with version 0
+const NAT_PMP_PORT: u16 = 5351;
+const PCP_VERSION: u8 = 2;
+const PCP_OPCODE_MAP: u8 = 1;
+const PCP_RESPONSE_LENGTH: usize = 60;
+/// Lifetime of the throwaway mapping PCP needs to report the external address
+const PCP_LIFETIME: u32 = 60;
+/// The discard port, mapped since PCP has no request that only returns the address
+const PCP_INTERNAL_PORT: u16 = 9;
+const PROTOCOL_UDP: u8 = 17;
+/// Requests are sent this many times, since UDP datagrams can get lost
+const ATTEMPTS: u32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RouterProtocol {
+    Upnp,
+    NatPmp,
+    Pcp,
+}
+
+pub struct RouterSource {
+    protocol: RouterProtocol,
+    /// `host` or `host:port`, the default gateway if unset. UPnP discovery is multicast
+    /// unless a gateway is given.
+    gateway: Option<String>,
+    timeout: Duration,
+}
+
+impl RouterSource {
+    pub fn new(protocol: RouterProtocol, gateway: Option<String>, timeout: Duration) -> Self {
+        Self {
+            protocol,
+            gateway,
+            timeout,
+        }
+    }
+
+    fn gateway_address(&self, default_port: u16) -> Result<SocketAddr, DnsBackendError> {
+        if let Some(gateway) = &self.gateway {
+            return resolve_server(gateway, default_port, Family::V4);
+        }
+        let gateway = default_net::get_default_gateway().map_err(|e| DnsBackendError {
+            message: format!("Couldn't find the default gateway: {e}"),
+        })?;
+        Ok(SocketAddr::new(gateway.ip_addr, default_port))
+    }
+
+    /// Send `request` to the gateway, retrying until a response arrives or we time out
+    fn exchange(&self, socket: &UdpSocket, request: &[u8]) -> Result<Vec<u8>, DnsBackendError> {
+        let io_error = |e: std::io::Error| DnsBackendError {
+            message: format!("{:?} request failed: {e}", self.protocol),
+        };
+        socket
+            .set_read_timeout(Some(self.timeout / ATTEMPTS))
+            .map_err(io_error)?;
+        let mut buffer = [0u8; 1100];
+        let mut last_error = None;
+        for _ in 0..ATTEMPTS {
+            socket.send(request).map_err(io_error)?;
+            match socket.recv(&mut buffer) {
+                Ok(length) => return Ok(buffer[..length].to_vec()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(io_error(last_error.unwrap()))
+    }
+
+    fn connect(&self) -> Result<UdpSocket, DnsBackendError> {
+        let gateway = self.gateway_address(NAT_PMP_PORT)?;
+        let io_error = |e: std::io::Error| DnsBackendError {
+            message: format!("Couldn't reach gateway {gateway}: {e}"),
+        };
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(io_error)?;
+        socket.connect(gateway).map_err(io_error)?;
+        Ok(socket)
+    }
+
+    fn nat_pmp(&self) -> Result<IpAddr, DnsBackendError> {
+        let socket = self.connect()?;
+        // Version 0, opcode 0: external address request
+        let response = self.exchange(&socket, &[0, 0])?;
+        if response.len() < 12 || response[0] != 0 || response[1] != 128 {
+            return Err(DnsBackendError {
+                message: "Invalid NAT-PMP response".to_string(),
+            });
+        }
+        let result = u16::from_be_bytes([response[2], response[3]]);
+        if result != 0 {
+            return Err(DnsBackendError {
+                message: format!("NAT-PMP request failed with result code {result}"),
+            });
+        }
+        let octets: [u8; 4] = response[8..12].try_into().unwrap();
+        Ok(IpAddr::V4(Ipv4Addr::from(octets)))
+    }
+
+    fn pcp_map_request(client: IpAddr, nonce: &[u8; 12], lifetime: u32) -> Vec<u8> {
+        let client = match client {
+            IpAddr::V4(address) => address.to_ipv6_mapped(),
+            IpAddr::V6(address) => address,
+        };
+        let mut request = vec![PCP_VERSION, PCP_OPCODE_MAP, 0, 0];
+        request.extend_from_slice(&lifetime.to_be_bytes());
+        request.extend_from_slice(&client.octets());
+        request.extend_from_slice(nonce);
+        request.extend_from_slice(&[PROTOCOL_UDP, 0, 0, 0]);
+        request.extend_from_slice(&PCP_INTERNAL_PORT.to_be_bytes());
+        // No preference for the external port or address
+        request.extend_from_slice(&0u16.to_be_bytes());
+        request.extend_from_slice(&Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+        request
+    }
+
+    fn pcp(&self) -> Result<IpAddr, DnsBackendError> {
+        let socket = self.connect()?;
+        let client = socket
+            .local_addr()
+            .map_err(|e| DnsBackendError {
+                message: format!("Couldn't get local address: {e}"),
+            })?
+            .ip();
+        let mut nonce = [0u8; 12];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| DnsBackendError {
+                message: "Failed to generate PCP nonce".to_string(),
+            })?;
+        let response = self.exchange(
+            &socket,
+            &Self::pcp_map_request(client, &nonce, PCP_LIFETIME),
+        )?;
+        // Remove the mapping again, it was only needed for the address
+        let _ = socket.send(&Self::pcp_map_request(client, &nonce, 0));
+        if response.len() < PCP_RESPONSE_LENGTH
+            || response[0] != PCP_VERSION
+            || response[1] != 0x80 | PCP_OPCODE_MAP
+            || response[24..36] != nonce
+        {
+            return Err(DnsBackendError {
+                message: "Invalid PCP response".to_string(),
+            });
+        }
+        if response[3] != 0 {
+            return Err(DnsBackendError {
+                message: format!("PCP request failed with result code {}", response[3]),
+            });
+        }
+        let octets: [u8; 16] = response[44..60].try_into().unwrap();
+        let address = Ipv6Addr::from(octets);
+        Ok(match address.to_ipv4_mapped() {
+            Some(address) => IpAddr::V4(address),
+            None => IpAddr::V6(address),
+        })
+    }
+
+    /// Find the gateway's device description URL with an SSDP M-SEARCH
+    fn ssdp_search(&self) -> Result<reqwest::Url, DnsBackendError> {
+        // Any host on the network can answer, so only trust the gateway when it's known
+        let (target, gateway) = match &self.gateway {
+            Some(gateway) => {
+                let target = resolve_server(gateway, SSDP_PORT, Family::V4)?;
+                (target, Some(target.ip()))
+            }
+            None => (
+                SSDP_MULTICAST,
+                default_net::get_default_gateway().ok().map(|x| x.ip_addr),
+            ),
+        };
+        let io_error = |e: std::io::Error| DnsBackendError {
+            message: format!("SSDP search failed: {e}"),
+        };
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(io_error)?;
+        let request = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_MULTICAST}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {IGD_DEVICE}\r\n\r\n"
+        );
+        socket
+            .send_to(request.as_bytes(), target)
+            .map_err(io_error)?;
+        let deadline = Instant::now() + self.timeout;
+        let mut buffer = [0u8; 2048];
+        // Other devices may answer too, so wait for one that points at a description
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            socket
+                .set_read_timeout(Some(remaining.max(Duration::from_millis(1))))
+                .map_err(io_error)?;
+            let (length, from) = socket.recv_from(&mut buffer).map_err(io_error)?;
+            if gateway.is_some_and(|x| x != from.ip()) {
+                debug!(
+                    "Ignoring SSDP reply from {}, which is not the gateway",
+                    from
+                );
+                continue;
+            }
+            let response = String::from_utf8_lossy(&buffer[..length]);
+            let location = response
+                .lines()
+                .filter_map(|x| x.split_once(':'))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("location"))
+                .and_then(|(_, value)| reqwest::Url::parse(value.trim()).ok());
+            if let Some(location) = location {
+                debug!("Found UPnP gateway {} at {}", from, location);
+                return Ok(location);
+            }
+        }
+        Err(DnsBackendError {
+            message: "No UPnP gateway answered the SSDP search".to_string(),
+        })
+    }
+
+    fn upnp(&self) -> Result<IpAddr, DnsBackendError> {
+        let location = self.ssdp_search()?;
+        let client = Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to setup UPnP client: {e:?}"),
+            })?;
+        let request_error = |e: reqwest::Error| DnsBackendError {
+            message: format!("UPnP request failed: {e:?}"),
+        };
+        let description = client
+            .get(location.clone())
+            .send()
+            .and_then(|x| x.error_for_status())
+            .and_then(|x| x.text())
+            .map_err(request_error)?;
+        let description = xml::parse(&description)?;
+        let (service_type, control_url) =
+            find_wan_service(&description).ok_or(DnsBackendError {
+                message: format!("{location} describes no WAN connection service"),
+            })?;
+        let base = description
+            .find("URLBase")
+            .and_then(|x| reqwest::Url::parse(x.text.trim()).ok())
+            .unwrap_or(location);
+        let control_url = base.join(control_url).map_err(|e| DnsBackendError {
+            message: format!("Invalid UPnP control URL {control_url}: {e}"),
+        })?;
+        let body = format!(
+            "<?xml version=\"1.0\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+             <s:Body><u:GetExternalIPAddress xmlns:u=\"{service_type}\"/></s:Body>\
+             </s:Envelope>"
+        );
+        let response = client
+            .post(control_url)
+            .header("Content-Type", "text/xml; charset=\"utf-8\"")
+            .header(
+                "SOAPAction",
+                format!("\"{service_type}#GetExternalIPAddress\""),
+            )
+            .body(body)
+            .send()
+            .and_then(|x| x.error_for_status())
+            .and_then(|x| x.text())
+            .map_err(request_error)?;
+        let address = xml::parse(&response)?
+            .find("NewExternalIPAddress")
+            .map(|x| x.text.trim().to_string())
+            .unwrap_or_default();
+        address.parse().map_err(|_| DnsBackendError {
+            message: format!("Router returned no WAN address: {address:?}"),
+        })
+    }
+}
+
+/// Service type and control URL of the first WANIPConnection or WANPPPConnection service
+fn find_wan_service(element: &xml::Element) -> Option<(&str, &str)> {
+    if element.name == "service" {
+        let service_type = element.child_text("serviceType").unwrap_or_default();
+        let wan = service_type.starts_with("urn:schemas-upnp-org:service:WANIPConnection:")
+            || service_type.starts_with("urn:schemas-upnp-org:service:WANPPPConnection:");
+        if let (true, Some(control_url)) = (wan, element.child_text("controlURL")) {
+            return Some((service_type, control_url));
+        }
+    }
+    element.children.iter().find_map(find_wan_service)
+}
+
+impl AddressSource for RouterSource {
+    fn name(&self) -> String {
+        let gateway = self.gateway.as_deref().unwrap_or("default gateway");
+        format!("{:?} via {gateway}", self.protocol)
+    }
+
+    fn discover(&self, family: Family) -> Result<IpAddr, DnsBackendError> {
+        let address = match self.protocol {
+            RouterProtocol::Upnp => self.upnp(),
+            RouterProtocol::NatPmp => self.nat_pmp(),
+            RouterProtocol::Pcp => self.pcp(),
+        }?;
+        // Private and shared addresses are allowed here, they're what nat_warning is for
+        if !family.matches(&address) || address.is_unspecified() {
+            return Err(DnsBackendError {
+                message: format!("Router reported unusable WAN address {address}"),
+            });
+        }
+        Ok(address)
+    }
+}
+
+/// Explain why the router's WAN address probably isn't reachable from the internet.
+/// `external` is the address seen by the other discovery sources, if any.
+pub fn nat_warning(router_wan: IpAddr, external: Option<IpAddr>) -> Option<String> {
+    if let IpAddr::V4(address) = router_wan {
+        if is_shared_address(&address) {
+            return Some(format!(
+                "Router WAN address {router_wan} is in the carrier-grade NAT range 100.64.0.0/10"
+            ));
+        }
+    }
+    if validate_address(router_wan, Family::V4).is_err() {
+        return Some(format!(
+            "Router WAN address {router_wan} is not public, the router is probably behind another NAT"
+        ));
+    }
+    match external {
+        Some(external) if external != router_wan => Some(format!(
+            "Router WAN address {router_wan} differs from the external address {external}, the connection is probably behind carrier-grade NAT"
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+    use std::thread;
+
+    /// Answer one UDP request with whatever `respond` builds from it
+    fn responder(respond: fn(&[u8]) -> Vec<u8>) -> (String, thread::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; 1100];
+            let (length, from) = socket.recv_from(&mut buffer).unwrap();
+            socket.send_to(&respond(&buffer[..length]), from).unwrap();
+        });
+        (address, handle)
+    }
+
+    fn source(protocol: RouterProtocol, gateway: String) -> RouterSource {
+        RouterSource::new(protocol, Some(gateway), Duration::from_secs(3))
+    }
+
+    #[test]
+    fn test_nat_pmp() {
+        let (gateway, handle) = responder(|request| {
+            assert_eq!(request, [0, 0]);
+            vec![0, 128, 0, 0, 0, 0, 0, 60, 100, 64, 12, 34]
+        });
+        let address = source(RouterProtocol::NatPmp, gateway)
+            .discover(Family::V4)
+            .unwrap();
+        assert_eq!(address.to_string(), "100.64.12.34");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_pcp() {
+        let (gateway, handle) = responder(|request| {
+            assert_eq!(request.len(), 60);
+            assert_eq!(request[..2], [PCP_VERSION, PCP_OPCODE_MAP]);
+            let mut response = vec![PCP_VERSION, 0x80 | PCP_OPCODE_MAP, 0, 0];
+            response.extend_from_slice(&PCP_LIFETIME.to_be_bytes());
+            response.extend_from_slice(&[0; 16]);
+            // Echo the nonce, protocol and ports, then the assigned external address
+            response.extend_from_slice(&request[24..42]);
+            response.extend_from_slice(&[0x30, 0x39]);
+            let external: Ipv4Addr = "93.184.216.34".parse().unwrap();
+            response.extend_from_slice(&external.to_ipv6_mapped().octets());
+            response
+        });
+        let address = source(RouterProtocol::Pcp, gateway)
+            .discover(Family::V4)
+            .unwrap();
+        assert_eq!(address.to_string(), "93.184.216.34");
+        handle.join().unwrap();
+    }
+
+    /// Answer an SSDP search and serve the device description and one SOAP response
+    fn upnp_discover(description: &'static str, response: &'static str) -> IpAddr {
+        let (url, http) = test_http::serve(2, move |request| {
+            if request.path == "/desc.xml" {
+                return Response::new(200, description);
+            }
+            assert_eq!(request.path, "/ctl/IPConn");
+            assert_eq!(
+                request.header("SOAPAction"),
+                Some("\"urn:schemas-upnp-org:service:WANIPConnection:1#GetExternalIPAddress\"")
+            );
+            Response::new(200, response)
+        });
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let gateway = socket.local_addr().unwrap().to_string();
+        let ssdp = thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+            let (length, from) = socket.recv_from(&mut buffer).unwrap();
+            let request = String::from_utf8_lossy(&buffer[..length]).to_string();
+            assert!(request.starts_with("M-SEARCH"));
+            assert!(request.contains(IGD_DEVICE));
+            let response =
+                format!("HTTP/1.1 200 OK\r\nST: {IGD_DEVICE}\r\nLOCATION: {url}/desc.xml\r\n\r\n");
+            socket.send_to(response.as_bytes(), from).unwrap();
+        });
+        let address = source(RouterProtocol::Upnp, gateway)
+            .discover(Family::V4)
+            .unwrap();
+        ssdp.join().unwrap();
+        http.join().unwrap();
+        address
+    }
+
+    #[test]
+    fn test_upnp() {
+        let address = upnp_discover(
+            "<root><device><serviceList>\
+             <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+             <controlURL>/l3f</controlURL></service>\
+             <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+             <controlURL>/ctl/IPConn</controlURL></service>\
+             </serviceList></device></root>",
+            "<s:Envelope><s:Body><u:GetExternalIPAddressResponse>\
+             <NewExternalIPAddress>93.184.216.34</NewExternalIPAddress>\
+             </u:GetExternalIPAddressResponse></s:Body></s:Envelope>",
+        );
+        assert_eq!(address.to_string(), "93.184.216.34");
+    }
+
+    #[test]
+    fn test_upnp_prefixes_and_attributes() {
+        // Some routers prefix every element and type the values with attributes
+        let address = upnp_discover(
+            "<?xml version=\"1.0\"?>\
+             <u:root xmlns:u=\"urn:schemas-upnp-org:device-1-0\"><u:device><u:serviceList>\
+             <u:service>\
+             <u:serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</u:serviceType>\
+             <u:controlURL> /ctl/IPConn </u:controlURL>\
+             </u:service></u:serviceList></u:device></u:root>",
+            "<?xml version=\"1.0\"?>\
+             <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\"><s:Body>\
+             <u:GetExternalIPAddressResponse xmlns:u=\"urn:schemas-upnp-org:service:WANIPConnection:1\">\
+             <NewExternalIPAddress xmlns:dt=\"urn:schemas-microsoft-com:datatypes\" dt:dt=\"string\">\
+             93.184.216.34</NewExternalIPAddress>\
+             </u:GetExternalIPAddressResponse></s:Body></s:Envelope>",
+        );
+        assert_eq!(address.to_string(), "93.184.216.34");
+    }
+
+    #[test]
+    fn test_ssdp_ignores_other_hosts() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let gateway = socket.local_addr().unwrap().to_string();
+        let ssdp = thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+            let (_, from) = socket.recv_from(&mut buffer).unwrap();
+            // Another device answering with its own description
+            let impostor = UdpSocket::bind("127.0.0.2:0").unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nST: {IGD_DEVICE}\r\nLOCATION: http://127.0.0.2:1/desc.xml\r\n\r\n"
+            );
+            impostor.send_to(response.as_bytes(), from).unwrap();
+        });
+        let source = RouterSource::new(
+            RouterProtocol::Upnp,
+            Some(gateway),
+            Duration::from_millis(500),
+        );
+        let error = source.ssdp_search().unwrap_err();
+        assert!(
+            error.message.starts_with("SSDP search failed"),
+            "{}",
+            error.message
+        );
+        ssdp.join().unwrap();
+    }
+
+    #[test]
+    fn test_nat_warning() {
+        let public: IpAddr = "93.184.216.34".parse().unwrap();
+        assert_eq!(nat_warning(public, Some(public)), None);
+        assert_eq!(nat_warning(public, None), None);
+        assert!(nat_warning("100.64.12.34".parse().unwrap(), None)
+            .unwrap()
+            .contains("carrier-grade NAT range"));
+        assert!(nat_warning("192.168.0.2".parse().unwrap(), None).is_some());
+        assert!(nat_warning(public, Some("93.184.216.35".parse().unwrap())).is_some());
+    }
+}
@@ -0,0 +1,222 @@
+use super::{resolve_server, validate_address, AddressSource, Family};
+use crate::dns_providers::DnsBackendError;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+const DEFAULT_PORT: u16 = 3478;
+const MAGIC_COOKIE: u32 = 0x2112_a442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const BINDING_ERROR: u16 = 0x0111;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const HEADER_LENGTH: usize = 20;
+/// Requests are sent this many times, since UDP datagrams can get lost
+const ATTEMPTS: u32 = 3;
+
+/// Learns the NAT mapped address with a STUN Binding Request (RFC 5389)
+pub struct StunSource {
+    server: String,
+    timeout: Duration,
+}
+
+impl StunSource {
+    /// `server` is `host` or `host:port`, the port defaulting to 3478
+    pub fn new(server: String, timeout: Duration) -> Self {
+        Self { server, timeout }
+    }
+}
+
+fn binding_request(transaction_id: &[u8; 12]) -> Vec<u8> {
+    let mut request = Vec::with_capacity(HEADER_LENGTH);
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction_id);
+    request
+}
+
+/// Decode a (XOR-)MAPPED-ADDRESS attribute value. XOR-MAPPED-ADDRESS masks the address
+/// with the magic cookie followed by the transaction ID.
+fn decode_address(value: &[u8], xor_mask: Option<&[u8; 16]>) -> Option<IpAddr> {
+    let family = *value.get(1)?;
+    let length = match family {
+        0x01 => 4,
+        0x02 => 16,
+        _ => return None,
+    };
+    let mut octets: Vec<u8> = value.get(4..4 + length)?.to_vec();
+    if let Some(mask) = xor_mask {
+        for (octet, mask) in octets.iter_mut().zip(mask.iter()) {
+            *octet ^= mask;
+        }
+    }
+    Some(match length {
+        4 => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(octets).ok()?)),
+        _ => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(octets).ok()?)),
+    })
+}
+
+/// Extract the mapped address from a Binding response to `transaction_id`
+fn parse_response(response: &[u8], transaction_id: &[u8; 12]) -> Result<IpAddr, DnsBackendError> {
+    let invalid = |reason: &str| DnsBackendError {
+        message: format!("Invalid STUN response: {reason}"),
+    };
+    if response.len() < HEADER_LENGTH {
+        return Err(invalid("too short"));
+    }
+    let kind = u16::from_be_bytes([response[0], response[1]]);
+    let length = u16::from_be_bytes([response[2], response[3]]) as usize;
+    if response[4..8] != MAGIC_COOKIE.to_be_bytes() || response[8..20] != transaction_id[..] {
+        return Err(invalid("transaction ID mismatch"));
+    }
+    match kind {
+        BINDING_SUCCESS => {}
+        BINDING_ERROR => {
+            return Err(DnsBackendError {
+                message: "STUN server returned a Binding error response".to_string(),
+            })
+        }
+        _ => return Err(invalid(&format!("unexpected message type {kind:#06x}"))),
+    }
+    let attributes = response
+        .get(HEADER_LENGTH..HEADER_LENGTH + length)
+        .ok_or(invalid("truncated"))?;
+    let mut xor_mask = [0u8; 16];
+    xor_mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    xor_mask[4..].copy_from_slice(transaction_id);
+
+    let mut mapped = None;
+    let mut offset = 0;
+    while offset + 4 <= attributes.len() {
+        let kind = u16::from_be_bytes([attributes[offset], attributes[offset + 1]]);
+        let length = u16::from_be_bytes([attributes[offset + 2], attributes[offset + 3]]) as usize;
+        let value = attributes
+            .get(offset + 4..offset + 4 + length)
+            .ok_or(invalid("truncated attribute"))?;
+        match kind {
+            ATTR_XOR_MAPPED_ADDRESS => {
+                return decode_address(value, Some(&xor_mask))
+                    .ok_or(invalid("bad XOR-MAPPED-ADDRESS"))
+            }
+            // Only sent by old RFC 3489 servers, so keep looking for XOR-MAPPED-ADDRESS
+            ATTR_MAPPED_ADDRESS => mapped = decode_address(value, None),
+            _ => {}
+        }
+        // Attribute values are padded to a multiple of four bytes
+        offset += 4 + length.div_ceil(4) * 4;
+    }
+    mapped.ok_or(invalid("no mapped address"))
+}
+
+impl AddressSource for StunSource {
+    fn name(&self) -> String {
+        format!("stun:{}", self.server)
+    }
+
+    fn discover(&self, family: Family) -> Result<IpAddr, DnsBackendError> {
+        let server = resolve_server(&self.server, DEFAULT_PORT, family)?;
+        let local: SocketAddr = match family {
+            Family::V4 => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            Family::V6 => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let io_error = |e: std::io::Error| DnsBackendError {
+            message: format!("STUN request to {server} failed: {e}"),
+        };
+        let socket = UdpSocket::bind(local).map_err(io_error)?;
+        socket
+            .set_read_timeout(Some(self.timeout / ATTEMPTS))
+            .map_err(io_error)?;
+        let mut transaction_id = [0u8; 12];
+        SystemRandom::new()
+            .fill(&mut transaction_id)
+            .map_err(|_| DnsBackendError {
+                message: "Failed to generate STUN transaction ID".to_string(),
+            })?;
+        let request = binding_request(&transaction_id);
+        let mut buffer = [0u8; 1024];
+        let mut last_error = None;
+        for _ in 0..ATTEMPTS {
+            socket.send_to(&request, server).map_err(io_error)?;
+            match socket.recv_from(&mut buffer) {
+                Ok((length, from)) if from == server => {
+                    let address = parse_response(&buffer[..length], &transaction_id)?;
+                    return validate_address(address, family);
+                }
+                Ok(_) => continue,
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(match last_error {
+            Some(e) => io_error(e),
+            None => DnsBackendError {
+                message: format!("No STUN response from {server}"),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Answer one Binding request with the given attribute
+    fn responder(attribute: fn(&[u8; 12]) -> Vec<u8>) -> (String, thread::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let mut buffer = [0u8; 512];
+            let (length, from) = socket.recv_from(&mut buffer).unwrap();
+            assert_eq!(length, HEADER_LENGTH);
+            assert_eq!(buffer[..2], BINDING_REQUEST.to_be_bytes());
+            let transaction_id: [u8; 12] = buffer[8..20].try_into().unwrap();
+            let attribute = attribute(&transaction_id);
+            let mut response = Vec::new();
+            response.extend_from_slice(&BINDING_SUCCESS.to_be_bytes());
+            response.extend_from_slice(&(attribute.len() as u16).to_be_bytes());
+            response.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+            response.extend_from_slice(&transaction_id);
+            response.extend_from_slice(&attribute);
+            socket.send_to(&response, from).unwrap();
+        });
+        (address, handle)
+    }
+
+    #[test]
+    fn test_xor_mapped_address() {
+        let (server, handle) = responder(|_| {
+            // 93.184.216.34:54321, XORed with the magic cookie
+            let mut attribute = vec![0x00, 0x20, 0x00, 0x08, 0x00, 0x01];
+            attribute.extend_from_slice(&(54321u16 ^ 0x2112).to_be_bytes());
+            attribute.extend_from_slice(&(0x5db8_d822u32 ^ MAGIC_COOKIE).to_be_bytes());
+            attribute
+        });
+        let source = StunSource::new(server, Duration::from_secs(3));
+        let address = source.discover(Family::V4).unwrap();
+        assert_eq!(address.to_string(), "93.184.216.34");
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_mapped_address_and_validation() {
+        // Plain MAPPED-ADDRESS carrying a private address, as a misconfigured server might
+        let (server, handle) = responder(|_| {
+            vec![
+                0x00, 0x01, 0x00, 0x08, 0x00, 0x01, 0x1f, 0x90, 192, 168, 1, 10,
+            ]
+        });
+        let error = StunSource::new(server, Duration::from_secs(3))
+            .discover(Family::V4)
+            .unwrap_err();
+        assert_eq!(error.message, "192.168.1.10 is not a public address");
+        handle.join().unwrap();
+
+        let transaction_id = [7u8; 12];
+        let mut response = vec![0x01, 0x01, 0x00, 0x00];
+        response.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        response.extend_from_slice(&[8u8; 12]);
+        assert!(parse_response(&response, &transaction_id).is_err());
+    }
+}
@@ -0,0 +1,516 @@
+use crate::dns_providers::DnsBackendError;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_NS: u16 = 2;
+pub const TYPE_SOA: u16 = 6;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_SRV: u16 = 33;
+pub const TYPE_TSIG: u16 = 250;
+pub const TYPE_AXFR: u16 = 252;
+pub const TYPE_ANY: u16 = 255;
+
+pub const CLASS_IN: u16 = 1;
+pub const CLASS_CH: u16 = 3;
+pub const CLASS_NONE: u16 = 254;
+pub const CLASS_ANY: u16 = 255;
+
+pub const OPCODE_QUERY: u8 = 0;
+pub const OPCODE_UPDATE: u8 = 5;
+
+pub const RCODE_NOERROR: u8 = 0;
+
+const MAX_LABEL_LENGTH: usize = 63;
+/// In wire format, including the length octets and the root label
+const MAX_NAME_LENGTH: usize = 255;
+
+const FLAG_QR: u16 = 0x8000;
+const FLAG_TC: u16 = 0x0200;
+const FLAG_RD: u16 = 0x0100;
+
+#[derive(Debug, PartialEq)]
+pub struct DnsMessageError {
+    pub message: String,
+}
+
+impl fmt::Display for DnsMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<DnsMessageError> for DnsBackendError {
+    fn from(e: DnsMessageError) -> Self {
+        DnsBackendError { message: e.message }
+    }
+}
+
+impl From<std::io::Error> for DnsMessageError {
+    fn from(e: std::io::Error) -> Self {
+        DnsMessageError {
+            message: format!("DNS transport error: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+    pub class: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceRecord {
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: Vec<u8>,
+}
+
+impl ResourceRecord {
+    /// Presentation form of the record data for the record types dns-agent manages
+    pub fn data_string(&self) -> Option<String> {
+        match self.rtype {
+            TYPE_A if self.rdata.len() == 4 => {
+                let octets: [u8; 4] = self.rdata[..].try_into().ok()?;
+                Some(Ipv4Addr::from(octets).to_string())
+            }
+            TYPE_AAAA if self.rdata.len() == 16 => {
+                let octets: [u8; 16] = self.rdata[..].try_into().ok()?;
+                Some(Ipv6Addr::from(octets).to_string())
+            }
+            TYPE_TXT => {
+                let mut out = String::new();
+                let mut pos = 0;
+                while pos < self.rdata.len() {
+                    let len = self.rdata[pos] as usize;
+                    let chunk = self.rdata.get(pos + 1..pos + 1 + len)?;
+                    out.push_str(&String::from_utf8_lossy(chunk));
+                    pos += 1 + len;
+                }
+                Some(out)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A DNS message. For UPDATE messages (RFC 2136) the four sections are
+/// interpreted as zone, prerequisite, update and additional.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Message {
+    pub id: u16,
+    pub flags: u16,
+    pub questions: Vec<Question>,
+    pub answers: Vec<ResourceRecord>,
+    pub authority: Vec<ResourceRecord>,
+    pub additional: Vec<ResourceRecord>,
+}
+
+impl Message {
+    pub fn new_query(id: u16, name: &str, qtype: u16, class: u16) -> Self {
+        Message {
+            id,
+            flags: FLAG_RD,
+            questions: vec![Question {
+                name: name.to_string(),
+                qtype,
+                class,
+            }],
+            ..Default::default()
+        }
+    }
+
+    pub fn new_update(id: u16, zone: &str) -> Self {
+        Message {
+            id,
+            flags: (OPCODE_UPDATE as u16) << 11,
+            questions: vec![Question {
+                name: zone.to_string(),
+                qtype: TYPE_SOA,
+                class: CLASS_IN,
+            }],
+            ..Default::default()
+        }
+    }
+
+    pub fn opcode(&self) -> u8 {
+        ((self.flags >> 11) & 0x0f) as u8
+    }
+
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0x000f) as u8
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.flags & FLAG_QR != 0
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.flags & FLAG_TC != 0
+    }
+
+    /// Build the response skeleton for this message, used by tests and stand-in servers
+    pub fn response(&self, rcode: u8) -> Message {
+        Message {
+            id: self.id,
+            flags: FLAG_QR | (self.flags & 0x7900) | rcode as u16,
+            questions: self.questions.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, DnsMessageError> {
+        let mut buf = Vec::with_capacity(512);
+        buf.extend_from_slice(&self.id.to_be_bytes());
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        for count in [
+            self.questions.len(),
+            self.answers.len(),
+            self.authority.len(),
+            self.additional.len(),
+        ] {
+            buf.extend_from_slice(&(count as u16).to_be_bytes());
+        }
+        for question in &self.questions {
+            encode_name(&question.name, &mut buf)?;
+            buf.extend_from_slice(&question.qtype.to_be_bytes());
+            buf.extend_from_slice(&question.class.to_be_bytes());
+        }
+        for record in self
+            .answers
+            .iter()
+            .chain(self.authority.iter())
+            .chain(self.additional.iter())
+        {
+            encode_record(record, &mut buf)?;
+        }
+        Ok(buf)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Message, DnsMessageError> {
+        let mut reader = Reader { data, pos: 0 };
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        let qdcount = reader.u16()?;
+        let ancount = reader.u16()?;
+        let nscount = reader.u16()?;
+        let arcount = reader.u16()?;
+        let mut questions = Vec::new();
+        for _ in 0..qdcount {
+            questions.push(Question {
+                name: reader.name()?,
+                qtype: reader.u16()?,
+                class: reader.u16()?,
+            });
+        }
+        let answers = reader.records(ancount as usize)?;
+        let authority = reader.records(nscount as usize)?;
+        let additional = reader.records(arcount as usize)?;
+        Ok(Message {
+            id,
+            flags,
+            questions,
+            answers,
+            authority,
+            additional,
+        })
+    }
+}
+
+/// Append a domain name in uncompressed wire format. Names that don't fit the wire
+/// format are rejected, since shortening them would address a different name.
+pub fn encode_name(name: &str, buf: &mut Vec<u8>) -> Result<(), DnsMessageError> {
+    let invalid = |reason: &str| DnsMessageError {
+        message: format!("Invalid DNS name {name}: {reason}"),
+    };
+    let start = buf.len();
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() {
+            continue;
+        }
+        let bytes = label.as_bytes();
+        if bytes.len() > MAX_LABEL_LENGTH {
+            return Err(invalid("label longer than 63 bytes"));
+        }
+        buf.push(bytes.len() as u8);
+        buf.extend_from_slice(bytes);
+    }
+    buf.push(0);
+    if buf.len() - start > MAX_NAME_LENGTH {
+        buf.truncate(start);
+        return Err(invalid("longer than 255 bytes"));
+    }
+    Ok(())
+}
+
+pub fn encode_record(record: &ResourceRecord, buf: &mut Vec<u8>) -> Result<(), DnsMessageError> {
+    encode_name(&record.name, buf)?;
+    buf.extend_from_slice(&record.rtype.to_be_bytes());
+    buf.extend_from_slice(&record.class.to_be_bytes());
+    buf.extend_from_slice(&record.ttl.to_be_bytes());
+    buf.extend_from_slice(&(record.rdata.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&record.rdata);
+    Ok(())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DnsMessageError> {
+        let slice = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(DnsMessageError {
+                message: "Truncated DNS message".to_string(),
+            })?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, DnsMessageError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, DnsMessageError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn name(&mut self) -> Result<String, DnsMessageError> {
+        let (name, end) = read_name(self.data, self.pos)?;
+        self.pos = end;
+        Ok(name)
+    }
+
+    fn records(&mut self, count: usize) -> Result<Vec<ResourceRecord>, DnsMessageError> {
+        let mut records = Vec::with_capacity(count);
+        for _ in 0..count {
+            let name = self.name()?;
+            let rtype = self.u16()?;
+            let class = self.u16()?;
+            let ttl = self.u32()?;
+            let rdlength = self.u16()? as usize;
+            let rdata = self.take(rdlength)?.to_vec();
+            records.push(ResourceRecord {
+                name,
+                rtype,
+                class,
+                ttl,
+                rdata,
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// Read a possibly compressed name starting at `start`, returning the dotted
+/// name (without the trailing dot) and the offset just past it.
+pub fn read_name(data: &[u8], start: usize) -> Result<(String, usize), DnsMessageError> {
+    let malformed = || DnsMessageError {
+        message: "Malformed name in DNS message".to_string(),
+    };
+    let mut labels: Vec<String> = Vec::new();
+    let mut pos = start;
+    let mut end = None;
+    let mut jumps = 0;
+    loop {
+        let len = *data.get(pos).ok_or_else(malformed)? as usize;
+        if len & 0xc0 == 0xc0 {
+            let low = *data.get(pos + 1).ok_or_else(malformed)? as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            jumps += 1;
+            if jumps > 64 {
+                return Err(malformed());
+            }
+            pos = ((len & 0x3f) << 8) | low;
+        } else if len == 0 {
+            let end = end.unwrap_or(pos + 1);
+            return Ok((labels.join("."), end));
+        } else {
+            let label = data.get(pos + 1..pos + 1 + len).ok_or_else(malformed)?;
+            labels.push(String::from_utf8_lossy(label).to_string());
+            pos += 1 + len;
+        }
+    }
+}
+
+/// Split the last record off the additional section of a raw message, returning
+/// the preceding bytes with ARCOUNT adjusted. Used to recover the exact bytes a
+/// TSIG MAC was computed over, since re-encoding would lose name compression.
+pub fn split_last_additional(data: &[u8]) -> Result<(Vec<u8>, ResourceRecord), DnsMessageError> {
+    let mut reader = Reader { data, pos: 4 };
+    let qdcount = reader.u16()?;
+    let ancount = reader.u16()?;
+    let nscount = reader.u16()?;
+    let arcount = reader.u16()?;
+    if arcount == 0 {
+        return Err(DnsMessageError {
+            message: "DNS message has no additional records".to_string(),
+        });
+    }
+    for _ in 0..qdcount {
+        reader.name()?;
+        reader.take(4)?;
+    }
+    // Summed as u16 this overflows for large counts, which a hostile server controls
+    let preceding = (ancount as usize)
+        .checked_add(nscount as usize)
+        .and_then(|x| x.checked_add(arcount as usize - 1))
+        .ok_or(DnsMessageError {
+            message: "DNS message record counts overflow".to_string(),
+        })?;
+    reader.records(preceding)?;
+    let start = reader.pos;
+    let record = reader.records(1)?.pop().unwrap();
+    let mut prefix = data[..start].to_vec();
+    prefix[10..12].copy_from_slice(&(arcount - 1).to_be_bytes());
+    Ok((prefix, record))
+}
+
+/// Send a message over UDP and wait for the response with a matching ID
+pub fn exchange_udp(
+    server: SocketAddr,
+    message: &Message,
+    timeout: Duration,
+) -> Result<Vec<u8>, DnsMessageError> {
+    let bind_addr: SocketAddr = match server {
+        SocketAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+        SocketAddr::V6(_) => "[::]:0".parse().unwrap(),
+    };
+    let socket = UdpSocket::bind(bind_addr)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(server)?;
+    socket.send(&message.encode()?)?;
+    let mut buf = vec![0u8; 65535];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        if len >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == message.id {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+/// Open a TCP connection to a DNS server
+pub fn connect_tcp(server: SocketAddr, timeout: Duration) -> Result<TcpStream, DnsMessageError> {
+    let stream = TcpStream::connect_timeout(&server, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(stream)
+}
+
+/// Write a single length-prefixed message to a DNS TCP stream
+pub fn write_tcp_message<W: Write>(stream: &mut W, data: &[u8]) -> Result<(), DnsMessageError> {
+    let mut framed = Vec::with_capacity(data.len() + 2);
+    framed.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    framed.extend_from_slice(data);
+    stream.write_all(&framed)?;
+    Ok(())
+}
+
+/// Read a single length-prefixed message from a DNS TCP stream
+pub fn read_tcp_message<R: Read>(stream: &mut R) -> Result<Vec<u8>, DnsMessageError> {
+    let mut len_buf = [0u8; 2];
+    stream.read_exact(&mut len_buf)?;
+    let mut buf = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+    stream.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_roundtrip() {
+        let mut message = Message::new_update(4242, "example.com");
+        message.authority.push(ResourceRecord {
+            name: "host.example.com".to_string(),
+            rtype: TYPE_A,
+            class: CLASS_IN,
+            ttl: 300,
+            rdata: vec![10, 0, 0, 1],
+        });
+        let decoded = Message::decode(&message.encode().unwrap()).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(decoded.opcode(), OPCODE_UPDATE);
+        assert_eq!(
+            decoded.authority[0].data_string(),
+            Some("10.0.0.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_read_compressed_name() {
+        // "example.com" at offset 0, then "www" + pointer to offset 0
+        let data = [
+            7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 3, b'w', b'w',
+            b'w', 0xc0, 0x00,
+        ];
+        let (name, end) = read_name(&data, 13).unwrap();
+        assert_eq!(name, "www.example.com");
+        assert_eq!(end, data.len());
+    }
+
+    #[test]
+    fn test_read_name_pointer_loop() {
+        let data = [0xc0, 0x00];
+        assert!(read_name(&data, 0).is_err());
+    }
+
+    #[test]
+    fn test_split_last_additional() {
+        let mut message = Message::new_update(4242, "example.com");
+        for last in [1, 2] {
+            message.additional.push(ResourceRecord {
+                name: "key".to_string(),
+                rtype: TYPE_A,
+                class: CLASS_IN,
+                ttl: 0,
+                rdata: vec![10, 0, 0, last],
+            });
+        }
+        let (prefix, record) = split_last_additional(&message.encode().unwrap()).unwrap();
+        assert_eq!(record.rdata, vec![10, 0, 0, 2]);
+        message.additional.pop();
+        assert_eq!(prefix, message.encode().unwrap());
+
+        // Counts that overflow a u16 sum must fail cleanly instead of panicking
+        let mut header = vec![0, 1, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0, 2];
+        header.extend_from_slice(&[0; 8]);
+        let error = split_last_additional(&header).unwrap_err();
+        assert_eq!(error.message, "Truncated DNS message");
+    }
+
+    #[test]
+    fn test_encode_rejects_oversized_names() {
+        let mut buf = Vec::new();
+        let label = "a".repeat(64);
+        let error = encode_name(&format!("{label}.example.com"), &mut buf).unwrap_err();
+        assert_eq!(
+            error.message,
+            format!("Invalid DNS name {label}.example.com: label longer than 63 bytes")
+        );
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert!(encode_name(&long, &mut buf).is_err());
+        assert!(buf.is_empty());
+        let longest = format!("{}.{}", vec!["a".repeat(63); 3].join("."), "a".repeat(61));
+        encode_name(&longest, &mut buf).unwrap();
+        assert_eq!(buf.len(), MAX_NAME_LENGTH);
+        let message = Message::new_query(1, &long, TYPE_A, CLASS_IN);
+        assert!(message.encode().is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::config::ParsedRecord;
+use crate::record_cache::RecordCache;
 use crate::update::{SystemAddress, SystemAddresses, SystemV4Address, SystemV6Address};
 
 use std::fmt;
@@ -72,11 +73,86 @@ impl fmt::Display for DnsBackendError {
     }
 }
 
+/// Expand a zone-relative record name (`@` for the apex) into a fully qualified name
+pub fn fqdn(name: &str, zone: &str) -> String {
+    let zone = zone.trim_end_matches('.');
+    if name == "@" || name.is_empty() {
+        zone.to_string()
+    } else {
+        format!("{}.{}", name, zone)
+    }
+}
+
+/// Convert a fully qualified name (with or without trailing dot) into a name relative
+/// to `zone`, using `@` for the apex. Names outside the zone are returned unchanged.
+pub fn relative_name(fqdn: &str, zone: &str) -> String {
+    let fqdn = fqdn.trim_end_matches('.');
+    let zone = zone.trim_end_matches('.');
+    if fqdn.eq_ignore_ascii_case(zone) {
+        return "@".to_string();
+    }
+    let suffix_start = fqdn.len().saturating_sub(zone.len() + 1);
+    match fqdn.get(suffix_start..) {
+        Some(suffix) if suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(zone) => {
+            fqdn[..suffix_start].to_string()
+        }
+        _ => fqdn.to_string(),
+    }
+}
+
+/// Normalize a record name from the config or a provider API into the form backends and
+/// `update_records` exchange: relative to `zone`, without a trailing dot and with `@` for
+/// the apex. Relative names may have several labels (`host.site1`), and fully qualified
+/// names are made relative to `zone`.
+pub fn normalize_name(name: &str, zone: &str) -> String {
+    let name = name.trim().trim_end_matches('.');
+    if name.is_empty() || name == "@" {
+        return "@".to_string();
+    }
+    relative_name(name, zone)
+}
+
+/// A normalized name with the apex as the empty string, as many provider APIs expect
+pub fn apex_as_empty(name: &str) -> &str {
+    if name == "@" {
+        ""
+    } else {
+        name
+    }
+}
+
+/// Whether a fully qualified name is `zone` itself or one of its subdomains
+pub fn in_zone(fqdn: &str, zone: &str) -> bool {
+    let fqdn = fqdn.trim_end_matches('.').to_ascii_lowercase();
+    let zone = zone.trim_end_matches('.').to_ascii_lowercase();
+    fqdn == zone || fqdn.ends_with(&format!(".{zone}"))
+}
+
 pub trait DnsBackend {
     fn zone(&self) -> String;
     fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError>;
     fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError>;
     fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError>;
+    /// Whether `update_record` replaces every record sharing the name and type (the whole
+    /// RRset). When true, names with multiple records are collapsed to the desired value
+    /// instead of being skipped.
+    fn updates_whole_rrset(&self) -> bool {
+        false
+    }
+    /// Write-only backends (such as dyndns2) can't list zone records. They return a local
+    /// cache here instead, and `update_records` compares against it rather than calling
+    /// `get_zone_records`.
+    fn record_cache(&self) -> Option<&RecordCache> {
+        None
+    }
+}
+
+fn remember_record(cache: Option<&RecordCache>, zone: &str, record: DnsRecord) {
+    if let Some(cache) = cache {
+        if let Err(e) = cache.store(zone, &record) {
+            warn!("{}", e);
+        }
+    }
 }
 
 pub fn update_records<T>(
@@ -87,16 +163,35 @@ pub fn update_records<T>(
 where
     T: DnsBackend,
 {
-    let current_records = backend.get_zone_records()?;
-    for desired_record in desired_records {
-        let zone_name = &backend.zone();
+    let cache = backend.record_cache();
+    let current_records = match cache {
+        Some(cache) => {
+            info!(
+                "Backend for {} is write-only, comparing against cached records",
+                backend.zone()
+            );
+            cache.records(&backend.zone())
+        }
+        None => backend.get_zone_records()?,
+    };
+    let zone_name = &backend.zone();
+    let current_records: Vec<DnsRecord> = current_records
+        .into_iter()
+        .map(|x| DnsRecord {
+            name: normalize_name(&x.name, zone_name),
+            ..x
+        })
+        .collect();
+    for mut desired_record in desired_records {
+        // Config names may be written fully qualified, with a trailing dot or empty for the apex
+        desired_record.name = normalize_name(&desired_record.name, zone_name);
         let mut matching_records: Vec<DnsRecord> = current_records
             .clone()
             .into_iter()
             .filter(|x| x.name() == desired_record.name && x.kind() == desired_record.record_type)
             .collect();
         let interface = find_matching_interface(&desired_record, system_interfaces)?;
-        if matching_records.len() > 1 {
+        if matching_records.len() > 1 && !backend.updates_whole_rrset() {
             warn!(
                 "Multiple records found for {}.{}, not updating",
                 &desired_record.name, zone_name
@@ -111,16 +206,44 @@ where
                 name: desired_record.name,
                 data: rec_data.clone(),
             };
-            backend.create_record(new_record)?;
+            backend.create_record(new_record.clone())?;
+            remember_record(cache, zone_name, new_record);
             info!(
                 "Created new {:?} record: {}.{} = {}",
                 rec_kind, rec_name, &zone_name, rec_data
             );
+        } else if matching_records.len() > 1 {
+            let current_ip = IpAddr::from(interface).to_string();
+            backend.update_record(&matching_records[0], &current_ip)?;
+            remember_record(
+                cache,
+                zone_name,
+                DnsRecord {
+                    data: current_ip.clone(),
+                    ..matching_records[0].clone()
+                },
+            );
+            info!(
+                "Replaced {} {:?} records for {}.{} with {}",
+                matching_records.len(),
+                desired_record.record_type,
+                &desired_record.name,
+                &zone_name,
+                current_ip
+            );
         } else if matching_records.len() == 1 {
             if let Some(record) = matching_records.pop() {
                 let current_ip = IpAddr::from(interface).to_string();
                 if record.data() != current_ip {
                     backend.update_record(&record, &current_ip)?;
+                    remember_record(
+                        cache,
+                        zone_name,
+                        DnsRecord {
+                            data: current_ip.clone(),
+                            ..record.clone()
+                        },
+                    );
                     info!(
                         "Updated {:?} record {}.{}: old {} new {}",
                         record.kind(),
@@ -191,7 +314,9 @@ pub fn find_matching_interface(
 
 #[cfg(test)]
 mod tests {
+    use std::cell::RefCell;
     use std::net::Ipv6Addr;
+    use std::rc::Rc;
 
     use super::*;
 
@@ -234,6 +359,116 @@ mod tests {
         assert_eq!(result.unwrap(), SystemAddress::V6(interface))
     }
 
+    struct RecordingBackend {
+        records: Vec<DnsRecord>,
+        whole_rrset: bool,
+        updates: Rc<RefCell<Vec<(DnsRecord, String)>>>,
+    }
+
+    impl DnsBackend for RecordingBackend {
+        fn zone(&self) -> String {
+            "example.com".to_string()
+        }
+        fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+            Ok(self.records.clone())
+        }
+        fn create_record(&self, _record: DnsRecord) -> Result<(), DnsBackendError> {
+            Ok(())
+        }
+        fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+            self.updates
+                .borrow_mut()
+                .push((record.clone(), new_data.to_string()));
+            Ok(())
+        }
+        fn updates_whole_rrset(&self) -> bool {
+            self.whole_rrset
+        }
+    }
+
+    #[test]
+    fn test_update_records_multiple_matches() {
+        let duplicate = |data: &str| DnsRecord {
+            kind: DnsRecordType::A,
+            name: "host".to_string(),
+            data: data.to_string(),
+        };
+        let interfaces = SystemAddresses {
+            v4_addresses: vec![SystemV4Address {
+                interface: "eth0".to_string(),
+                address: IpAddr::from(std::net::Ipv4Addr::new(10, 1, 1, 1)),
+            }],
+            v6_addresses: Vec::new(),
+        };
+        for whole_rrset in [false, true] {
+            let updates = Rc::new(RefCell::new(Vec::new()));
+            let backend = RecordingBackend {
+                records: vec![duplicate("10.0.0.1"), duplicate("10.0.0.2")],
+                whole_rrset,
+                updates: updates.clone(),
+            };
+            let desired = vec![ParsedRecord {
+                name: "host".to_string(),
+                record_type: DnsRecordType::A,
+                interface: "eth0".to_string(),
+            }];
+            update_records(backend, desired, &interfaces).unwrap();
+            let updates = updates.borrow();
+            if whole_rrset {
+                assert_eq!(updates.len(), 1);
+                assert_eq!(updates[0].1, "10.1.1.1");
+            } else {
+                assert!(updates.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("www", "example.com"), "www");
+        assert_eq!(normalize_name("host.site1", "example.com"), "host.site1");
+        assert_eq!(
+            normalize_name("www.dev.example.com.", "example.com"),
+            "www.dev"
+        );
+        assert_eq!(normalize_name("example.com", "example.com."), "@");
+        assert_eq!(normalize_name("", "example.com"), "@");
+        assert_eq!(normalize_name("@", "example.com"), "@");
+        assert_eq!(apex_as_empty("@"), "");
+        assert_eq!(apex_as_empty("host.site1"), "host.site1");
+    }
+
+    #[test]
+    fn test_update_records_multi_label_name() {
+        let record = |name: &str| DnsRecord {
+            kind: DnsRecordType::A,
+            name: name.to_string(),
+            data: "10.0.0.1".to_string(),
+        };
+        let interfaces = SystemAddresses {
+            v4_addresses: vec![SystemV4Address {
+                interface: "eth0".to_string(),
+                address: IpAddr::from(std::net::Ipv4Addr::new(10, 1, 1, 1)),
+            }],
+            v6_addresses: Vec::new(),
+        };
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let backend = RecordingBackend {
+            records: vec![record("www"), record("www.dev.example.com.")],
+            whole_rrset: false,
+            updates: updates.clone(),
+        };
+        let desired = vec![ParsedRecord {
+            name: "www.dev".to_string(),
+            record_type: DnsRecordType::A,
+            interface: "eth0".to_string(),
+        }];
+        update_records(backend, desired, &interfaces).unwrap();
+        let updates = updates.borrow();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].0.name, "www.dev");
+    }
+
     #[test]
     fn test_find_matching_interface_no_match() {
         let record = ParsedRecord {
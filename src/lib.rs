@@ -2,6 +2,12 @@
 extern crate log;
 
 pub mod config;
+pub mod discovery;
+pub mod dns_message;
 pub mod dns_providers;
 pub mod providers;
+pub mod record_cache;
 pub mod update;
+
+#[cfg(test)]
+mod test_http;
@@ -0,0 +1,191 @@
+use crate::dns_providers::{
+    fqdn, in_zone, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::Client;
+use serde_derive::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AdGuardHomeBackendConfig {
+    /// Base URL of the AdGuard Home web interface, e.g. `http://adguard.lan:3000`
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Rewrite {
+    domain: String,
+    answer: String,
+}
+
+#[derive(Serialize, Debug)]
+struct RewriteUpdate<'a> {
+    target: &'a Rewrite,
+    update: &'a Rewrite,
+}
+
+/// Manages AdGuard Home DNS rewrites whose answer is an IP address
+pub struct AdGuardHomeBackend {
+    client: Client,
+    url: String,
+    username: String,
+    password: String,
+    zone: String,
+}
+
+impl AdGuardHomeBackend {
+    pub fn new(config: AdGuardHomeBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            url: config.url.trim_end_matches('/').to_string(),
+            username: config.username,
+            password: config.password,
+            zone: zone.trim_end_matches('.').to_string(),
+        }
+    }
+
+    fn send(&self, request: reqwest::blocking::RequestBuilder) -> Result<String, DnsBackendError> {
+        let response = request
+            .basic_auth(&self.username, Some(&self.password))
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!("AdGuard Home API returned {status}: {}", text.trim()),
+            });
+        }
+        Ok(text)
+    }
+
+    fn rewrite(&self, record: &DnsRecord, data: &str) -> Result<Rewrite, DnsBackendError> {
+        match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => Ok(Rewrite {
+                domain: fqdn(&record.name, &self.zone),
+                answer: data.to_string(),
+            }),
+            _ => Err(DnsBackendError {
+                message: "AdGuard Home rewrites only support A and AAAA records".to_string(),
+            }),
+        }
+    }
+}
+
+impl DnsBackend for AdGuardHomeBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let text = self.send(
+            self.client
+                .get(format!("{}/control/rewrite/list", self.url)),
+        )?;
+        let rewrites: Vec<Rewrite> = serde_json::from_str(&text).map_err(|e| DnsBackendError {
+            message: format!("Could not parse AdGuard Home API response: {e:?}"),
+        })?;
+        debug!("Fetched rewrites from adguard home: {:?}", rewrites);
+        Ok(rewrites
+            .into_iter()
+            .filter(|x| in_zone(&x.domain, &self.zone))
+            .filter_map(|x| {
+                // Rewrites to other domain names act as CNAMEs and aren't managed here.
+                // The answer is kept as stored, since updates have to name it exactly.
+                let address: IpAddr = x.answer.parse().ok()?;
+                Some(DnsRecord {
+                    kind: if address.is_ipv4() {
+                        DnsRecordType::A
+                    } else {
+                        DnsRecordType::AAAA
+                    },
+                    name: relative_name(&x.domain, &self.zone),
+                    data: x.answer,
+                })
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let rewrite = self.rewrite(&record, &record.data)?;
+        self.send(
+            self.client
+                .post(format!("{}/control/rewrite/add", self.url))
+                .json(&rewrite),
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let body = RewriteUpdate {
+            target: &self.rewrite(record, &record.data)?,
+            update: &self.rewrite(record, new_data)?,
+        };
+        self.send(
+            self.client
+                .put(format!("{}/control/rewrite/update", self.url))
+                .json(&body),
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_rewrites() {
+        let (url, handle) = test_http::serve(3, |request| {
+            if request.path == "/control/rewrite/list" {
+                Response::new(
+                    200,
+                    r#"[{"domain":"nas.home.lan","answer":"192.168.1.10"},
+                        {"domain":"tv.home.lan","answer":"nas.home.lan"},
+                        {"domain":"nas.home.lan","answer":"2001:DB8:0::1"},
+                        {"domain":"nas.other.lan","answer":"10.0.0.1"}]"#,
+                )
+            } else {
+                Response::new(200, "")
+            }
+        });
+        let config = AdGuardHomeBackendConfig {
+            url,
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+        };
+        let backend = AdGuardHomeBackend::new(config, "home.lan".to_string());
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "nas");
+        assert_eq!(records[1].kind, DnsRecordType::AAAA);
+        assert_eq!(records[1].data, "2001:DB8:0::1");
+        backend.update_record(&records[0], "192.168.1.11").unwrap();
+        backend.update_record(&records[1], "2001:db8::2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(
+            requests[0].header("authorization"),
+            Some("Basic YWRtaW46c2VjcmV0")
+        );
+        assert_eq!(requests[1].method, "PUT");
+        assert_eq!(requests[1].path, "/control/rewrite/update");
+        assert_eq!(
+            requests[1].body,
+            r#"{"target":{"domain":"nas.home.lan","answer":"192.168.1.10"},"update":{"domain":"nas.home.lan","answer":"192.168.1.11"}}"#
+        );
+        assert_eq!(
+            requests[2].body,
+            r#"{"target":{"domain":"nas.home.lan","answer":"2001:DB8:0::1"},"update":{"domain":"nas.home.lan","answer":"2001:db8::2"}}"#
+        );
+    }
+}
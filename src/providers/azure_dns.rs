@@ -0,0 +1,345 @@
+use super::oauth::{request_token, TokenCache};
+use crate::dns_providers::{DnsBackend, DnsBackendError, DnsRecord, DnsRecordType};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ARM_URL: &str = "https://management.azure.com";
+const DEFAULT_AUTHORITY: &str = "https://login.microsoftonline.com";
+const API_VERSION: &str = "2018-05-01";
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AzureDnsBackendConfig {
+    pub tenant_id: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub subscription_id: String,
+    pub resource_group: String,
+    pub ttl: Option<u32>,
+    /// Override the Azure Resource Manager URL, e.g. for sovereign clouds or testing
+    pub arm_url: Option<String>,
+    /// Override the OAuth2 token endpoint, defaults to the tenant's v2.0 endpoint
+    pub token_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct ARecord {
+    #[serde(rename = "ipv4Address")]
+    ipv4_address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct AaaaRecord {
+    #[serde(rename = "ipv6Address")]
+    ipv6_address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct TxtRecord {
+    value: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct RecordSetProperties {
+    #[serde(rename = "TTL")]
+    ttl: u32,
+    #[serde(rename = "ARecords", skip_serializing_if = "Option::is_none")]
+    a_records: Option<Vec<ARecord>>,
+    #[serde(rename = "AAAARecords", skip_serializing_if = "Option::is_none")]
+    aaaa_records: Option<Vec<AaaaRecord>>,
+    #[serde(rename = "TXTRecords", skip_serializing_if = "Option::is_none")]
+    txt_records: Option<Vec<TxtRecord>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordSet {
+    name: String,
+    properties: RecordSetProperties,
+}
+
+#[derive(Serialize, Debug)]
+struct RecordSetUpdate {
+    properties: RecordSetProperties,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RecordSetsResponse {
+    value: Vec<RecordSet>,
+    next_link: Option<String>,
+}
+
+impl RecordSet {
+    fn records(self) -> Vec<DnsRecord> {
+        let mut records = Vec::new();
+        let mut push = |kind: DnsRecordType, data: String| {
+            records.push(DnsRecord {
+                kind,
+                name: self.name.clone(),
+                data,
+            })
+        };
+        for x in self.properties.a_records.unwrap_or_default() {
+            push(DnsRecordType::A, x.ipv4_address);
+        }
+        for x in self.properties.aaaa_records.unwrap_or_default() {
+            push(DnsRecordType::AAAA, x.ipv6_address);
+        }
+        for x in self.properties.txt_records.unwrap_or_default() {
+            push(DnsRecordType::TXT, x.value.concat());
+        }
+        records
+    }
+}
+
+pub struct AzureDnsBackend {
+    client: Client,
+    config: AzureDnsBackendConfig,
+    arm_url: String,
+    token_url: String,
+    tokens: TokenCache,
+    zone: String,
+    ttl: u32,
+}
+
+impl AzureDnsBackend {
+    pub fn new(config: AzureDnsBackendConfig, zone: String) -> Self {
+        let arm_url = config
+            .arm_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_ARM_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        let token_url = config.token_url.clone().unwrap_or_else(|| {
+            format!("{DEFAULT_AUTHORITY}/{}/oauth2/v2.0/token", config.tenant_id)
+        });
+        Self {
+            client: Client::new(),
+            arm_url,
+            token_url,
+            tokens: TokenCache::default(),
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+            config,
+        }
+    }
+
+    fn access_token(&self) -> Result<String, DnsBackendError> {
+        self.tokens.get(|| {
+            // Resources are addressed through the ARM URL, and so is the token's audience
+            let scope = format!("{}/.default", self.arm_url);
+            request_token(
+                &self.client,
+                &self.token_url,
+                &[
+                    ("grant_type", "client_credentials"),
+                    ("client_id", &self.config.client_id),
+                    ("client_secret", &self.config.client_secret),
+                    ("scope", &scope),
+                ],
+            )
+        })
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let response = request
+            .bearer_auth(self.access_token()?)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Azure DNS API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Azure DNS API response: {e:?}"),
+        })
+    }
+
+    fn zone_url(&self) -> String {
+        format!(
+            "{}/subscriptions/{}/resourceGroups/{}/providers/Microsoft.Network/dnsZones/{}",
+            self.arm_url, self.config.subscription_id, self.config.resource_group, self.zone
+        )
+    }
+
+    fn _get_record_sets_internal(&self) -> Result<Vec<RecordSet>, DnsBackendError> {
+        let mut record_sets = Vec::new();
+        let mut request = self
+            .client
+            .get(format!("{}/recordsets", self.zone_url()))
+            .query(&[("api-version", API_VERSION)]);
+        loop {
+            let response: RecordSetsResponse = self.send(request)?;
+            record_sets.extend(response.value);
+            // nextLink already carries the api-version and continuation token
+            match response.next_link {
+                Some(next_link) => request = self.client.get(next_link),
+                None => break,
+            }
+        }
+        debug!(
+            "Fetched record sets from azure for domain {}: {:?}",
+            &self.zone, record_sets
+        );
+        Ok(record_sets)
+    }
+
+    /// Replace the record set for the record's name and type with a single value
+    fn put_record_set(&self, record: &DnsRecord, data: &str) -> Result<(), DnsBackendError> {
+        let mut properties = RecordSetProperties {
+            ttl: self.ttl,
+            ..Default::default()
+        };
+        match record.kind {
+            DnsRecordType::A => {
+                properties.a_records = Some(vec![ARecord {
+                    ipv4_address: data.to_string(),
+                }])
+            }
+            DnsRecordType::AAAA => {
+                properties.aaaa_records = Some(vec![AaaaRecord {
+                    ipv6_address: data.to_string(),
+                }])
+            }
+            DnsRecordType::TXT => {
+                properties.txt_records = Some(vec![TxtRecord {
+                    value: vec![data.to_string()],
+                }])
+            }
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Record type not supported".to_string(),
+                })
+            }
+        }
+        let kind: String = record.kind.clone().into();
+        let request = self
+            .client
+            .put(format!("{}/{}/{}", self.zone_url(), kind, record.name))
+            .query(&[("api-version", API_VERSION)])
+            .json(&RecordSetUpdate { properties });
+        self.send::<serde::de::IgnoredAny>(request)?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for AzureDnsBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            ._get_record_sets_internal()?
+            .into_iter()
+            .flat_map(RecordSet::records)
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self.put_record_set(&record, &record.data)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        self.put_record_set(record, new_data)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {record:?} with value {new_data}: {e}"
+                ),
+            })
+    }
+
+    fn updates_whole_rrset(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_client_credentials_and_record_sets() {
+        let (url, handle) = test_http::serve(4, |request| {
+            if request.path == "/tenant/token" {
+                Response::new(
+                    200,
+                    r#"{"token_type":"Bearer","expires_in":3599,"access_token":"arm-token"}"#,
+                )
+            } else if request.path.contains("skipToken") {
+                Response::new(
+                    200,
+                    r#"{"value":[{"name":"@","type":"Microsoft.Network/dnszones/AAAA","properties":{"TTL":300,"AAAARecords":[{"ipv6Address":"2001:db8::1"}]}}]}"#,
+                )
+            } else if request.method == "GET" {
+                Response::new(
+                    200,
+                    &format!(
+                        r#"{{"value":[{{"name":"www","type":"Microsoft.Network/dnszones/A","properties":{{"TTL":300,"ARecords":[{{"ipv4Address":"192.0.2.1"}},{{"ipv4Address":"192.0.2.2"}}]}}}}],
+                            "nextLink":"{}/next?api-version=2018-05-01&$skipToken=abc"}}"#,
+                        request
+                            .header("host")
+                            .map(|x| format!("http://{x}"))
+                            .unwrap()
+                    ),
+                )
+            } else {
+                Response::new(200, r#"{"name":"www"}"#)
+            }
+        });
+        let config = AzureDnsBackendConfig {
+            tenant_id: "tenant".to_string(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            subscription_id: "sub".to_string(),
+            resource_group: "dns".to_string(),
+            ttl: Some(300),
+            arm_url: Some(url.clone()),
+            token_url: Some(format!("{url}/tenant/token")),
+        };
+        let backend = AzureDnsBackend::new(config, "example.com".to_string());
+        let records = backend.get_zone_records().unwrap();
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend.update_record(&record, "192.0.2.3").unwrap();
+        let requests = handle.join().unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].name, "@");
+        assert_eq!(records[2].kind, DnsRecordType::AAAA);
+        assert!(requests[0].body.contains("grant_type=client_credentials"));
+        assert!(requests[0].body.contains("client_secret=secret"));
+        assert_eq!(
+            requests[1].path,
+            "/subscriptions/sub/resourceGroups/dns/providers/Microsoft.Network/dnsZones/example.com/recordsets?api-version=2018-05-01"
+        );
+        assert_eq!(
+            requests[1].header("authorization"),
+            Some("Bearer arm-token")
+        );
+        assert_eq!(requests[3].method, "PUT");
+        assert_eq!(
+            requests[3].path,
+            "/subscriptions/sub/resourceGroups/dns/providers/Microsoft.Network/dnsZones/example.com/A/www?api-version=2018-05-01"
+        );
+        assert_eq!(
+            requests[3].body,
+            r#"{"properties":{"TTL":300,"ARecords":[{"ipv4Address":"192.0.2.3"}]}}"#
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use crate::dns_providers::{
+    apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.bunny.net";
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BunnyBackendConfig {
+    /// Account API key
+    pub api_key: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+/// Bunny identifies record types by number
+fn type_number(kind: &DnsRecordType) -> Result<u8, DnsBackendError> {
+    match kind {
+        DnsRecordType::A => Ok(0),
+        DnsRecordType::AAAA => Ok(1),
+        DnsRecordType::TXT => Ok(3),
+        _ => Err(DnsBackendError {
+            message: "Record type not supported".to_string(),
+        }),
+    }
+}
+
+fn type_from_number(number: u8) -> DnsRecordType {
+    match number {
+        0 => DnsRecordType::A,
+        1 => DnsRecordType::AAAA,
+        3 => DnsRecordType::TXT,
+        8 => DnsRecordType::SRV,
+        12 => DnsRecordType::NS,
+        _ => DnsRecordType::Other,
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+struct BunnyRecord {
+    id: u64,
+    #[serde(rename = "Type")]
+    kind: u8,
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Zone {
+    id: u64,
+    domain: String,
+    records: Vec<BunnyRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ZonesResponse {
+    items: Vec<Zone>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct RecordRequest<'a> {
+    #[serde(rename = "Type")]
+    kind: u8,
+    name: &'a str,
+    value: &'a str,
+    ttl: u32,
+}
+
+pub struct BunnyBackend {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    zone_id: u64,
+    zone: String,
+    ttl: u32,
+}
+
+impl BunnyBackend {
+    /// Create the backend, looking up the Bunny DNS zone ID for `zone`
+    pub fn new(config: BunnyBackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let mut backend = Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: config.api_key,
+            zone_id: 0,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        };
+        let request = backend
+            .client
+            .get(format!("{}/dnszone", backend.endpoint))
+            .query(&[("search", &backend.zone)]);
+        let response: ZonesResponse = backend.parse(backend.send(request)?)?;
+        let zone = response
+            .items
+            .into_iter()
+            .find(|x| x.domain.eq_ignore_ascii_case(&backend.zone))
+            .ok_or(DnsBackendError {
+                message: format!("Zone {} not found in Bunny DNS account", backend.zone),
+            })?;
+        debug!("Resolved bunny zone {} to ID {}", zone.domain, zone.id);
+        backend.zone_id = zone.id;
+        Ok(backend)
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<Response, DnsBackendError> {
+        let response = request
+            .header("AccessKey", &self.api_key)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Bunny API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        Ok(response)
+    }
+
+    fn parse<T: DeserializeOwned>(&self, response: Response) -> Result<T, DnsBackendError> {
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Bunny API response: {e:?}"),
+        })
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<BunnyRecord>, DnsBackendError> {
+        let request = self
+            .client
+            .get(format!("{}/dnszone/{}", self.endpoint, self.zone_id));
+        let zone: Zone = self.parse(self.send(request)?)?;
+        debug!(
+            "Fetched records from bunny for domain {}: {:?}",
+            &self.zone, zone.records
+        );
+        Ok(zone.records)
+    }
+}
+
+impl DnsBackend for BunnyBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            ._get_records_internal()?
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: type_from_number(x.kind),
+                name: normalize_name(&x.name, &self.zone),
+                data: x.value,
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let body = RecordRequest {
+            kind: type_number(&record.kind)?,
+            name: apex_as_empty(&record.name),
+            value: &record.data,
+            ttl: self.ttl,
+        };
+        let request = self
+            .client
+            .put(format!(
+                "{}/dnszone/{}/records",
+                self.endpoint, self.zone_id
+            ))
+            .json(&body);
+        self.send(request).map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let kind = type_number(&record.kind)?;
+        let existing_record = self
+            ._get_records_internal()?
+            .into_iter()
+            .find(|x| {
+                x.name == apex_as_empty(&record.name) && x.kind == kind && x.value == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let body = RecordRequest {
+            kind,
+            name: apex_as_empty(&record.name),
+            value: new_data,
+            ttl: self.ttl,
+        };
+        // Bunny creates records with PUT and updates them with POST
+        let request = self
+            .client
+            .post(format!(
+                "{}/dnszone/{}/records/{}",
+                self.endpoint, self.zone_id, existing_record.id
+            ))
+            .json(&body);
+        self.send(request).map_err(|e| DnsBackendError {
+            message: format!(
+                "Failed to update DNS record {existing_record:?} with value {new_data}: {e}"
+            ),
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    const ZONE: &str = r#"{"Id":7,"Domain":"example.com","Records":[
+        {"Id":1,"Type":0,"Name":"","Value":"192.0.2.1","Ttl":300},
+        {"Id":2,"Type":1,"Name":"www","Value":"2001:db8::1","Ttl":300},
+        {"Id":3,"Type":3,"Name":"www","Value":"v=spf1 -all","Ttl":300},
+        {"Id":4,"Type":12,"Name":"","Value":"kiki.bunny.net","Ttl":300},
+        {"Id":5,"Type":2,"Name":"alias","Value":"www.example.com","Ttl":300}
+    ]}"#;
+
+    fn serve(count: usize) -> (String, std::thread::JoinHandle<Vec<test_http::Request>>) {
+        test_http::serve(count, |request| match request.path.as_str() {
+            x if x.starts_with("/dnszone?") => Response::new(
+                200,
+                r#"{"Items":[{"Id":6,"Domain":"example.com.au","Records":[]},{"Id":7,"Domain":"example.com","Records":[]}],"HasMoreItems":false}"#,
+            ),
+            "/dnszone/7" => Response::new(200, ZONE),
+            _ => Response::new(201, "{}"),
+        })
+    }
+
+    fn backend(url: &str) -> BunnyBackend {
+        let config = BunnyBackendConfig {
+            api_key: "key".to_string(),
+            ttl: None,
+            endpoint: Some(url.to_string()),
+        };
+        BunnyBackend::new(config, "example.com".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_record_types() {
+        let (url, handle) = serve(2);
+        let records = backend(&url).get_zone_records().unwrap();
+        let kinds: Vec<_> = records.iter().map(|x| x.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DnsRecordType::A,
+                DnsRecordType::AAAA,
+                DnsRecordType::TXT,
+                DnsRecordType::NS,
+                DnsRecordType::Other
+            ]
+        );
+        assert_eq!(records[0].name, "@");
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].path, "/dnszone?search=example.com");
+        assert_eq!(requests[0].header("AccessKey"), Some("key"));
+        assert!(type_number(&DnsRecordType::SRV).is_err());
+    }
+
+    #[test]
+    fn test_create_with_put_and_update_with_post() {
+        let (url, handle) = serve(4);
+        let backend = backend(&url);
+        backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::TXT,
+                name: "@".to_string(),
+                data: "hello".to_string(),
+            })
+            .unwrap();
+        let record = DnsRecord {
+            kind: DnsRecordType::AAAA,
+            name: "www".to_string(),
+            data: "2001:db8::1".to_string(),
+        };
+        backend.update_record(&record, "2001:db8::2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[1].method, "PUT");
+        assert_eq!(requests[1].path, "/dnszone/7/records");
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(body["Type"], 3);
+        assert_eq!(body["Name"], "");
+        assert_eq!(requests[3].method, "POST");
+        assert_eq!(requests[3].path, "/dnszone/7/records/2");
+        let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+        assert_eq!(body["Type"], 1);
+        assert_eq!(body["Value"], "2001:db8::2");
+        assert_eq!(body["Ttl"], DEFAULT_TTL);
+    }
+}
@@ -3,83 +3,294 @@ use cloudflare::endpoints::dns::dns::{
     ListDnsRecordsParams, UpdateDnsRecord, UpdateDnsRecordParams,
 };
 
+use cloudflare::endpoints::zones::zone::{ListZones, ListZonesParams};
 use cloudflare::framework::auth::Credentials;
 use cloudflare::framework::client::blocking_api::HttpApiClient;
 use cloudflare::framework::client::ClientConfig;
-use cloudflare::framework::Environment::Production;
+use cloudflare::framework::Environment;
+use reqwest::blocking::Client;
 use serde_derive::{Deserialize, Serialize};
+use std::cell::RefCell;
 
-use crate::dns_providers::{self, DnsBackend, DnsBackendError, DnsRecordType};
+use crate::dns_providers::{
+    self, fqdn, normalize_name, DnsBackend, DnsBackendError, DnsRecordType,
+};
+
+const DEFAULT_ENDPOINT: &str = "https://api.cloudflare.com/client/v4";
+const DEFAULT_TTL: u32 = 3600;
+const PAGE_SIZE: u32 = 100;
 
 pub struct CloudFlareBackend {
     zone_identifier: String,
     client: HttpApiClient,
     zone: String,
+    endpoint: String,
+    /// Used for the record fields cloudflare-rs doesn't expose (comment and tags)
+    http_client: Client,
+    auth_headers: Vec<(&'static str, String)>,
+    defaults: CloudFlareRecordOptions,
+    records: Vec<CloudFlareRecordConfig>,
+    /// Records returned by the last `get_zone_records`, so updates don't have to list the
+    /// zone again to find the record ID
+    known_records: RefCell<Vec<DnsRecord>>,
+}
+
+/// Cloudflare specific record settings. Unset values fall back to the backend-wide
+/// defaults; when those are unset too, updates keep the record's current proxied and
+/// TTL values and new records are created unproxied with a TTL of 3600.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct CloudFlareRecordOptions {
+    pub proxied: Option<bool>,
+    /// TTL in seconds, 1 means automatic
+    pub ttl: Option<u32>,
+    pub comment: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl CloudFlareRecordOptions {
+    fn or(self, defaults: &CloudFlareRecordOptions) -> CloudFlareRecordOptions {
+        CloudFlareRecordOptions {
+            proxied: self.proxied.or(defaults.proxied),
+            ttl: self.ttl.or(defaults.ttl),
+            comment: self.comment.or_else(|| defaults.comment.clone()),
+            tags: self.tags.or_else(|| defaults.tags.clone()),
+        }
+    }
+}
+
+/// Settings for the records matching `name` (and `record_type`, when given)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CloudFlareRecordConfig {
+    pub name: String,
+    pub record_type: Option<String>,
+    #[serde(flatten)]
+    pub options: CloudFlareRecordOptions,
+}
+
+#[derive(Serialize, Debug)]
+struct RecordMetadata<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<&'a [String]>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CloudFlareBackendConfig {
-    pub api_token: String,
-    pub zone_identifier: String,
+    /// Scoped API token, preferred over the global API key
+    pub api_token: Option<String>,
+    /// Account email, used together with `api_key`
+    pub email: Option<String>,
+    /// Legacy global API key
+    pub api_key: Option<String>,
+    /// Looked up from `zone` when not set
+    pub zone_identifier: Option<String>,
     pub zone: String,
+    /// Defaults for every record in the zone
+    #[serde(flatten)]
+    pub defaults: CloudFlareRecordOptions,
+    /// Per-record overrides of the defaults
+    pub records: Option<Vec<CloudFlareRecordConfig>>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
 }
 
-impl From<DnsRecord> for dns_providers::DnsRecord {
-    fn from(value: DnsRecord) -> Self {
-        let (record_type, data) = match value.content {
+impl CloudFlareBackend {
+    pub fn new(config: CloudFlareBackendConfig) -> Result<Self, DnsBackendError> {
+        let (credentials, auth_headers) = match config {
+            CloudFlareBackendConfig {
+                api_token: Some(token),
+                ..
+            } => (
+                Credentials::UserAuthToken {
+                    token: token.clone(),
+                },
+                vec![("Authorization", format!("Bearer {token}"))],
+            ),
+            CloudFlareBackendConfig {
+                email: Some(email),
+                api_key: Some(key),
+                ..
+            } => (
+                Credentials::UserAuthKey {
+                    email: email.clone(),
+                    key: key.clone(),
+                },
+                vec![("X-Auth-Email", email), ("X-Auth-Key", key)],
+            ),
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Cloudflare backend needs either api_token or email and api_key"
+                        .to_string(),
+                })
+            }
+        };
+        let endpoint = config
+            .endpoint
+            .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        // cloudflare-rs joins request paths onto the environment URL, so it needs the slash,
+        // and it panics on URLs it can't parse
+        if let Err(e) = reqwest::Url::parse(&endpoint) {
+            return Err(DnsBackendError {
+                message: format!("Invalid Cloudflare endpoint {endpoint}: {e}"),
+            });
+        }
+        let environment = Environment::Custom(format!("{endpoint}/"));
+        let client = HttpApiClient::new(credentials, ClientConfig::default(), environment)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to setup cloudflare client: {e:?}"),
+            })?;
+        let mut backend = CloudFlareBackend {
+            zone_identifier: String::new(),
+            client,
+            zone: config.zone,
+            endpoint,
+            http_client: Client::new(),
+            auth_headers,
+            defaults: config.defaults,
+            records: config.records.unwrap_or_default(),
+            known_records: RefCell::new(Vec::new()),
+        };
+        backend.zone_identifier = match config.zone_identifier {
+            Some(zone_identifier) => zone_identifier,
+            None => backend._lookup_zone_identifier()?,
+        };
+        Ok(backend)
+    }
+
+    fn _lookup_zone_identifier(&self) -> Result<String, DnsBackendError> {
+        let list_zones_request = ListZones {
+            params: ListZonesParams {
+                name: Some(self.zone.clone()),
+                ..Default::default()
+            },
+        };
+        let zones = self
+            .client
+            .request(&list_zones_request)
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error {e:?}"),
+            })?
+            .result;
+        let zone = zones
+            .into_iter()
+            .find(|x| x.name.eq_ignore_ascii_case(&self.zone))
+            .ok_or(DnsBackendError {
+                message: format!("Zone {} not found in Cloudflare account", self.zone),
+            })?;
+        debug!("Resolved cloudflare zone {} to ID {}", zone.name, zone.id);
+        Ok(zone.id)
+    }
+
+    /// Convert an API record, whose name is fully qualified
+    fn to_record(&self, value: &DnsRecord) -> dns_providers::DnsRecord {
+        let (record_type, data) = match &value.content {
             DnsContent::A { content } => (DnsRecordType::A, content.to_string()),
             DnsContent::AAAA { content } => (DnsRecordType::AAAA, content.to_string()),
             _ => (DnsRecordType::Other, "".to_string()),
         };
-        let non_fqdn = value.name.split('.').next().unwrap();
         dns_providers::DnsRecord {
             kind: record_type,
-            name: non_fqdn.to_string(),
+            name: normalize_name(&value.name, &self.zone),
             data,
         }
     }
-}
 
-impl From<CloudFlareBackendConfig> for CloudFlareBackend {
-    fn from(value: CloudFlareBackendConfig) -> Self {
-        // let credentials = Credentials::UserAuthKey {
-        //     email: value.email,
-        //     key: value.api_key,
-        // };
-        let credentials = Credentials::UserAuthToken {
-            token: value.api_token,
-        };
-        let client = HttpApiClient::new(credentials, ClientConfig::default(), Production).unwrap();
-        CloudFlareBackend {
-            zone_identifier: value.zone_identifier,
-            client,
-            zone: value.zone,
+    /// Resolve the settings for a record, per-record values taking precedence
+    // `Option::is_none_or` would need Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    fn options_for(&self, record: &dns_providers::DnsRecord) -> CloudFlareRecordOptions {
+        self.records
+            .iter()
+            .find(|x| {
+                normalize_name(&x.name, &self.zone).eq_ignore_ascii_case(&record.name)
+                    && x.record_type.as_ref().map_or(true, |kind| {
+                        DnsRecordType::from(kind.as_str()) == record.kind
+                    })
+            })
+            .map(|x| x.options.clone())
+            .unwrap_or_default()
+            .or(&self.defaults)
+    }
+
+    /// Set the record comment and tags, which cloudflare-rs has no parameters for
+    fn _set_metadata(
+        &self,
+        identifier: &str,
+        options: &CloudFlareRecordOptions,
+    ) -> Result<(), DnsBackendError> {
+        if options.comment.is_none() && options.tags.is_none() {
+            return Ok(());
         }
+        let body = RecordMetadata {
+            comment: options.comment.as_deref(),
+            tags: options.tags.as_deref(),
+        };
+        let mut request = self
+            .http_client
+            .patch(format!(
+                "{}/zones/{}/dns_records/{identifier}",
+                self.endpoint, self.zone_identifier
+            ))
+            .json(&body);
+        for (name, value) in &self.auth_headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send().map_err(|e| DnsBackendError {
+            message: format!("Dns backend error: {e:?}"),
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Cloudflare API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        Ok(())
     }
-}
 
-impl CloudFlareBackend {
+    /// List records, following pages until a short one. `name` narrows the query to a
+    /// single fully qualified name.
     fn _get_zone_records_internal(
         &self,
+        name: Option<String>,
     ) -> Result<Vec<DnsRecord>, crate::dns_providers::DnsBackendError> {
-        let params = ListDnsRecordsParams::default();
-        let list_records_request = ListDnsRecords {
-            zone_identifier: &self.zone_identifier,
-            params,
-        };
-        match self.client.request(&list_records_request) {
-            Ok(resp) => {
-                let res_vec = resp.result;
-                debug!(
-                    "Fetched records from cloudflare for domain {}: {:?}",
-                    &self.zone_identifier, res_vec
-                );
-                Ok(res_vec)
+        let mut records = Vec::new();
+        let mut page = 1;
+        loop {
+            let list_records_request = ListDnsRecords {
+                zone_identifier: &self.zone_identifier,
+                params: ListDnsRecordsParams {
+                    name: name.clone(),
+                    page: Some(page),
+                    per_page: Some(PAGE_SIZE),
+                    ..Default::default()
+                },
+            };
+            let res_vec = match self.client.request(&list_records_request) {
+                Ok(resp) => resp.result,
+                Err(e) => {
+                    return Err(DnsBackendError {
+                        message: format!("Dns backend error {e:?}"),
+                    })
+                }
+            };
+            let last_page = res_vec.len() < PAGE_SIZE as usize;
+            records.extend(res_vec);
+            if last_page {
+                break;
             }
-            Err(e) => Err(DnsBackendError {
-                message: format!("Dns backend error {e:?}"),
-            }),
+            page += 1;
         }
+        debug!(
+            "Fetched records from cloudflare for domain {}: {:?}",
+            &self.zone_identifier, records
+        );
+        Ok(records)
     }
 }
 
@@ -90,8 +301,10 @@ impl DnsBackend for CloudFlareBackend {
     fn get_zone_records(
         &self,
     ) -> Result<Vec<crate::dns_providers::DnsRecord>, crate::dns_providers::DnsBackendError> {
-        let records = self._get_zone_records_internal()?;
-        Ok(records.into_iter().map(|x| x.into()).collect())
+        let records = self._get_zone_records_internal(None)?;
+        let converted = records.iter().map(|x| self.to_record(x)).collect();
+        *self.known_records.borrow_mut() = records;
+        Ok(converted)
     }
     fn create_record(&self, record: dns_providers::DnsRecord) -> Result<(), DnsBackendError> {
         let content = match record.kind {
@@ -107,12 +320,13 @@ impl DnsBackend for CloudFlareBackend {
                 })
             }
         };
+        let options = self.options_for(&record);
+        let name = fqdn(&record.name, &self.zone);
         let params = CreateDnsRecordParams {
-            ttl: Some(3600),
+            ttl: Some(options.ttl.unwrap_or(DEFAULT_TTL)),
             priority: None,
-            // TODO: Make proxied configurable
-            proxied: Some(false),
-            name: &record.name,
+            proxied: Some(options.proxied.unwrap_or(false)),
+            name: &name,
             content,
         };
         let create_record_request = CreateDnsRecord {
@@ -120,9 +334,15 @@ impl DnsBackend for CloudFlareBackend {
             params,
         };
         match self.client.request(&create_record_request) {
-            Ok(_) => {
+            Ok(resp) => {
                 info!("Created record {create_record_request:?}");
-                Ok(())
+                self._set_metadata(&resp.result.id, &options)
+                    .map_err(|e| DnsBackendError {
+                        message: format!(
+                            "Created {} but it has no comment or tags, setting them failed: {}",
+                            record.name, e
+                        ),
+                    })
             }
             Err(e) => Err(DnsBackendError {
                 message: format!("Dns backend error while creating {}: {:?}", record.name, e),
@@ -134,31 +354,25 @@ impl DnsBackend for CloudFlareBackend {
         record: &dns_providers::DnsRecord,
         new_data: &str,
     ) -> Result<(), DnsBackendError> {
-        let records = self._get_zone_records_internal()?;
-        let existing_record = records
-            .iter()
-            .find(|x| {
-                let mut record_type_match = false;
-                match x.content {
-                    DnsContent::A { content: _ } => {
-                        if record.kind == DnsRecordType::A {
-                            record_type_match = true
-                        }
-                    }
-                    DnsContent::AAAA { content: _ } => {
-                        if record.kind == DnsRecordType::AAAA {
-                            record_type_match = true
-                        }
-                    }
-                    _ => {
-                        error!("Unsupported record type specified")
-                    }
-                }
-                x.name.split('.').next().unwrap() == record.name && record_type_match
-            })
-            .ok_or(DnsBackendError {
-                message: "Tried to update a nonexistant record".to_string(),
-            })?;
+        let is_match = |x: &DnsRecord| {
+            let converted = self.to_record(x);
+            converted.name == record.name
+                && converted.kind == record.kind
+                && converted.data == record.data
+        };
+        let position = self.known_records.borrow().iter().position(is_match);
+        let known_record = position.map(|x| self.known_records.borrow_mut().remove(x));
+        let existing_record = match known_record {
+            Some(existing_record) => existing_record,
+            // Not listed before, so only query the records with this name
+            None => self
+                ._get_zone_records_internal(Some(fqdn(&record.name, &self.zone)))?
+                .into_iter()
+                .find(is_match)
+                .ok_or(DnsBackendError {
+                    message: "Tried to update a nonexistant record".to_string(),
+                })?,
+        };
 
         let content = match record.kind {
             DnsRecordType::A => DnsContent::A {
@@ -174,10 +388,13 @@ impl DnsBackend for CloudFlareBackend {
             }
         };
 
+        // Keep what was set in the dashboard unless the config says otherwise
+        let options = self.options_for(record);
+        let name = fqdn(&record.name, &self.zone);
         let params = UpdateDnsRecordParams {
-            ttl: Some(3600),
-            proxied: Some(false),
-            name: &record.name,
+            ttl: Some(options.ttl.unwrap_or(existing_record.ttl)),
+            proxied: Some(options.proxied.unwrap_or(existing_record.proxied)),
+            name: &name,
             content,
         };
 
@@ -189,7 +406,13 @@ impl DnsBackend for CloudFlareBackend {
         match self.client.request(&update_record_request) {
             Ok(_) => {
                 info!("Updated record {update_record_request:?}");
-                Ok(())
+                self._set_metadata(&existing_record.id, &options)
+                    .map_err(|e| DnsBackendError {
+                        message: format!(
+                            "Updated {} but not its comment and tags, setting them failed: {}",
+                            record.name, e
+                        ),
+                    })
             }
             Err(e) => Err(DnsBackendError {
                 message: format!("Dns backend error while updating {}: {:?}", record.name, e),
@@ -197,3 +420,249 @@ impl DnsBackend for CloudFlareBackend {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn config(url: &str) -> CloudFlareBackendConfig {
+        CloudFlareBackendConfig {
+            api_token: Some("token".to_string()),
+            email: None,
+            api_key: None,
+            zone_identifier: Some("z1".to_string()),
+            zone: "example.com".to_string(),
+            defaults: CloudFlareRecordOptions::default(),
+            records: None,
+            endpoint: Some(url.to_string()),
+        }
+    }
+
+    /// Wrap `result` in the envelope every Cloudflare API response has
+    fn envelope(result: &str) -> String {
+        format!(r#"{{"success":true,"errors":[],"messages":[],"result":{result}}}"#)
+    }
+
+    fn zone_json(id: &str, name: &str) -> String {
+        format!(
+            r##"{{"id":"{id}","name":"{name}","account":{{"id":"a1","name":"Account"}},"activated_on":"2024-01-01T00:00:00Z","created_on":"2024-01-01T00:00:00Z","modified_on":"2024-01-01T00:00:00Z","development_mode":0,"meta":{{"custom_certificate_quota":0,"page_rule_quota":3,"phishing_detected":false,"multiple_railguns_allowed":false}},"name_servers":["a.ns.cloudflare.com"],"original_dnshost":null,"original_name_servers":null,"original_registrar":null,"owner":{{"type":"user","id":"u1","email":"user@example.com"}},"paused":false,"permissions":["#zone:read"],"plan":null,"status":"active","type":"full","vanity_name_servers":[]}}"##
+        )
+    }
+
+    fn record_json(id: &str, name: &str, content: &str) -> String {
+        format!(
+            r#"{{"id":"{id}","zone_id":"z1","zone_name":"example.com","name":"{name}","type":"A","content":"{content}","proxiable":true,"proxied":true,"ttl":300,"locked":false,"meta":{{"auto_added":false}},"created_on":"2024-01-01T00:00:00Z","modified_on":"2024-01-01T00:00:00Z"}}"#
+        )
+    }
+
+    #[test]
+    fn test_zone_lookup() {
+        let (url, handle) = test_http::serve(1, |_| {
+            Response::new(
+                200,
+                &envelope(&format!("[{}]", zone_json("z9", "Example.com"))),
+            )
+        });
+        let backend = CloudFlareBackend::new(CloudFlareBackendConfig {
+            zone_identifier: None,
+            ..config(&url)
+        })
+        .unwrap();
+        assert_eq!(backend.zone_identifier, "z9");
+        let requests = handle.join().unwrap();
+        assert!(requests[0].path.starts_with("/zones?"));
+        assert!(requests[0].path.contains("name=example.com"));
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer token"));
+    }
+
+    #[test]
+    fn test_new_errors() {
+        let error = CloudFlareBackend::new(CloudFlareBackendConfig {
+            api_token: None,
+            email: Some("user@example.com".to_string()),
+            ..config("http://127.0.0.1:9")
+        })
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.message,
+            "Cloudflare backend needs either api_token or email and api_key"
+        );
+        let error = CloudFlareBackend::new(config("not a url")).err().unwrap();
+        assert!(
+            error
+                .message
+                .starts_with("Invalid Cloudflare endpoint not a url"),
+            "{}",
+            error.message
+        );
+        let (url, handle) = test_http::serve(1, |_| {
+            Response::new(
+                200,
+                &envelope(&format!("[{}]", zone_json("z9", "example.org"))),
+            )
+        });
+        let error = CloudFlareBackend::new(CloudFlareBackendConfig {
+            zone_identifier: None,
+            ..config(&url)
+        })
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.message,
+            "Zone example.com not found in Cloudflare account"
+        );
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_create_with_api_key_and_metadata() {
+        let (url, handle) = test_http::serve(2, |_| {
+            Response::new(
+                200,
+                &envelope(&record_json("r1", "www.example.com", "192.0.2.1")),
+            )
+        });
+        let backend = CloudFlareBackend::new(CloudFlareBackendConfig {
+            api_token: None,
+            email: Some("user@example.com".to_string()),
+            api_key: Some("key".to_string()),
+            defaults: CloudFlareRecordOptions {
+                comment: Some("managed by dns-agent".to_string()),
+                tags: Some(vec!["owner:agent".to_string()]),
+                ..Default::default()
+            },
+            ..config(&format!("{url}/"))
+        })
+        .unwrap();
+        backend
+            .create_record(dns_providers::DnsRecord {
+                kind: DnsRecordType::A,
+                name: "www".to_string(),
+                data: "192.0.2.1".to_string(),
+            })
+            .unwrap();
+        let requests = handle.join().unwrap();
+        for request in &requests {
+            assert_eq!(request.header("X-Auth-Email"), Some("user@example.com"));
+            assert_eq!(request.header("X-Auth-Key"), Some("key"));
+            assert_eq!(request.header("Authorization"), None);
+        }
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/zones/z1/dns_records");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["name"], "www.example.com");
+        assert_eq!(body["ttl"], DEFAULT_TTL);
+        assert_eq!(body["proxied"], false);
+        assert_eq!(requests[1].method, "PATCH");
+        assert_eq!(requests[1].path, "/zones/z1/dns_records/r1");
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"comment": "managed by dns-agent", "tags": ["owner:agent"]})
+        );
+    }
+
+    #[test]
+    fn test_pages_and_known_records() {
+        let (url, handle) = test_http::serve(3, |request| {
+            if request.method == "PUT" {
+                Response::new(
+                    200,
+                    &envelope(&record_json("r0", "host0.example.com", "192.0.2.9")),
+                )
+            } else if request.path.contains("page=1&") {
+                let records: Vec<String> = (0..PAGE_SIZE)
+                    .map(|x| {
+                        record_json(
+                            &format!("r{x}"),
+                            &format!("host{x}.example.com"),
+                            "192.0.2.1",
+                        )
+                    })
+                    .collect();
+                Response::new(200, &envelope(&format!("[{}]", records.join(","))))
+            } else {
+                Response::new(
+                    200,
+                    &envelope(&format!(
+                        "[{}]",
+                        record_json("r100", "example.com", "192.0.2.2")
+                    )),
+                )
+            }
+        });
+        let backend = CloudFlareBackend::new(config(&url)).unwrap();
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records.len(), PAGE_SIZE as usize + 1);
+        assert_eq!(records[0].name, "host0");
+        assert_eq!(records[100].name, "@");
+        assert_eq!(records[100].data, "192.0.2.2");
+        // The record ID comes from the listing above, so no further list call is made
+        backend.update_record(&records[0], "192.0.2.9").unwrap();
+        let requests = handle.join().unwrap();
+        assert!(requests[0].path.contains("page=1&"));
+        assert!(requests[1].path.contains("page=2&"));
+        assert!(requests[1].path.contains("per_page=100"));
+        assert_eq!(requests[2].method, "PUT");
+        assert_eq!(requests[2].path, "/zones/z1/dns_records/r0");
+        let body: serde_json::Value = serde_json::from_str(&requests[2].body).unwrap();
+        assert_eq!(body["content"], "192.0.2.9");
+        // Proxied and TTL are kept from the existing record
+        assert_eq!(body["proxied"], true);
+        assert_eq!(body["ttl"], 300);
+    }
+
+    #[test]
+    fn test_record_options_and_metadata_failure() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.method == "PATCH" {
+                Response::new(
+                    403,
+                    r#"{"success":false,"errors":[{"code":10000,"message":"Authentication error"}]}"#,
+                )
+            } else {
+                Response::new(
+                    200,
+                    &envelope(&record_json("r1", "www.example.com", "192.0.2.1")),
+                )
+            }
+        });
+        let backend = CloudFlareBackend::new(CloudFlareBackendConfig {
+            records: Some(vec![CloudFlareRecordConfig {
+                name: "WWW.example.com.".to_string(),
+                record_type: Some("A".to_string()),
+                options: CloudFlareRecordOptions {
+                    proxied: Some(true),
+                    comment: Some("router".to_string()),
+                    ..Default::default()
+                },
+            }]),
+            ..config(&url)
+        })
+        .unwrap();
+        let record = dns_providers::DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        assert_eq!(backend.options_for(&record).proxied, Some(true));
+        let aaaa = dns_providers::DnsRecord {
+            kind: DnsRecordType::AAAA,
+            ..record.clone()
+        };
+        assert_eq!(backend.options_for(&aaaa).proxied, None);
+        let error = backend.create_record(record).unwrap_err();
+        assert!(
+            error
+                .message
+                .starts_with("Created www but it has no comment or tags, setting them failed: Cloudflare API returned 403 Forbidden"),
+            "{}",
+            error.message
+        );
+        let requests = handle.join().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["proxied"], true);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::dns_providers::{
+    apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_ENDPOINT: &str = "https://desec.io/api/v1";
+// deSEC's minimum TTL for most accounts
+const DEFAULT_TTL: u32 = 3600;
+// Throttled requests are retried this many times before giving up
+const MAX_RETRIES: u32 = 5;
+// Longer waits (e.g. the daily limits) fail the run instead of blocking it
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DesecBackendConfig {
+    pub token: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RRSet {
+    subname: String,
+    #[serde(rename = "type")]
+    kind: String,
+    records: Vec<String>,
+    ttl: u32,
+}
+
+pub struct DesecBackend {
+    client: Client,
+    endpoint: String,
+    token: String,
+    zone: String,
+    ttl: u32,
+}
+
+/// Seconds to wait from a `Retry-After` header, if it holds a delay in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get("retry-after")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// `url` of the `rel="next"` entry of a `Link` header
+fn next_link(response: &Response) -> Option<String> {
+    let link = response.headers().get("link")?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        if params.contains("rel=\"next\"") {
+            Some(
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string(),
+            )
+        } else {
+            None
+        }
+    })
+}
+
+impl DesecBackend {
+    pub fn new(config: DesecBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            token: config.token,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    /// Send a request, sleeping and retrying while deSEC answers 429 Too Many Requests
+    fn send(&self, request: RequestBuilder) -> Result<Response, DnsBackendError> {
+        let mut attempt = 0;
+        loop {
+            let this_request = request.try_clone().ok_or(DnsBackendError {
+                message: "Dns backend error: request can't be retried".to_string(),
+            })?;
+            let response = this_request
+                .header("Authorization", format!("Token {}", self.token))
+                .send()
+                .map_err(|e| DnsBackendError {
+                    message: format!("Dns backend error: {e:?}"),
+                })?;
+            let status = response.status();
+            if status == StatusCode::TOO_MANY_REQUESTS && attempt < MAX_RETRIES {
+                let wait = retry_after(&response).unwrap_or(Duration::from_secs(1));
+                if wait <= MAX_RETRY_WAIT {
+                    attempt += 1;
+                    info!(
+                        "deSEC is rate limiting requests, retrying in {}s",
+                        wait.as_secs()
+                    );
+                    thread::sleep(wait);
+                    continue;
+                }
+                return Err(DnsBackendError {
+                    message: format!(
+                        "deSEC throttled the request and asks to wait {}s before retrying, \
+                         more than the {}s waited at most",
+                        wait.as_secs(),
+                        MAX_RETRY_WAIT.as_secs()
+                    ),
+                });
+            }
+            if !status.is_success() {
+                return Err(DnsBackendError {
+                    message: format!(
+                        "deSEC API returned {status}: {}",
+                        response.text().unwrap_or_default()
+                    ),
+                });
+            }
+            return Ok(response);
+        }
+    }
+
+    fn parse<T: DeserializeOwned>(response: Response) -> Result<T, DnsBackendError> {
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse deSEC API response: {e:?}"),
+        })
+    }
+
+    fn _get_rrsets_internal(&self) -> Result<Vec<RRSet>, DnsBackendError> {
+        let mut rrsets = Vec::new();
+        // Zones with more than 500 RRsets are paginated with cursors in the Link header
+        let mut url = Some(format!("{}/domains/{}/rrsets/", self.endpoint, self.zone));
+        while let Some(current) = url {
+            let response = self.send(self.client.get(current))?;
+            url = next_link(&response);
+            let page: Vec<RRSet> = Self::parse(response)?;
+            rrsets.extend(page);
+        }
+        debug!(
+            "Fetched rrsets from desec for domain {}: {:?}",
+            &self.zone, rrsets
+        );
+        Ok(rrsets)
+    }
+
+    fn rrset(&self, record: &DnsRecord, data: &str) -> Result<RRSet, DnsBackendError> {
+        let value = match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => data.to_string(),
+            DnsRecordType::TXT => format!("\"{}\"", data.replace('"', "\\\"")),
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Record type not supported".to_string(),
+                })
+            }
+        };
+        Ok(RRSet {
+            subname: apex_as_empty(&record.name).to_string(),
+            kind: record.kind.clone().into(),
+            records: vec![value],
+            ttl: self.ttl,
+        })
+    }
+}
+
+impl DnsBackend for DesecBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let mut records = Vec::new();
+        for rrset in self._get_rrsets_internal()? {
+            let kind: DnsRecordType = rrset.kind.as_str().into();
+            let name = normalize_name(&rrset.subname, &self.zone);
+            for value in rrset.records {
+                let data = match kind {
+                    DnsRecordType::TXT => value.trim_matches('"').replace("\\\"", "\""),
+                    _ => value,
+                };
+                records.push(DnsRecord {
+                    kind: kind.clone(),
+                    name: name.clone(),
+                    data,
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        // Bulk PATCH creates the RRset if it doesn't exist yet
+        let body = vec![self.rrset(&record, &record.data)?];
+        let request = self
+            .client
+            .patch(format!("{}/domains/{}/rrsets/", self.endpoint, self.zone))
+            .json(&body);
+        self.send(request).map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let body = self.rrset(record, new_data)?;
+        let kind: String = record.kind.clone().into();
+        let path_name = match apex_as_empty(&record.name) {
+            "" => "@",
+            name => name,
+        };
+        let request = self
+            .client
+            .put(format!(
+                "{}/domains/{}/rrsets/{}/{}/",
+                self.endpoint, self.zone, path_name, kind
+            ))
+            .json(&body);
+        self.send(request).map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })?;
+        Ok(())
+    }
+
+    fn updates_whole_rrset(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn backend(endpoint: &str) -> DesecBackend {
+        let config = DesecBackendConfig {
+            token: "secret".to_string(),
+            ttl: None,
+            endpoint: Some(endpoint.to_string()),
+        };
+        DesecBackend::new(config, "example.dedyn.io".to_string())
+    }
+
+    #[test]
+    fn test_rate_limit_is_retried() {
+        let mut calls = 0;
+        let (url, handle) = test_http::serve(2, move |_| {
+            calls += 1;
+            if calls == 1 {
+                Response::new(429, r#"{"detail":"Request was throttled."}"#)
+                    .header("Retry-After", "1")
+            } else {
+                Response::new(
+                    200,
+                    r#"[{"subname":"","type":"AAAA","records":["2001:db8::1"],"ttl":3600},
+                        {"subname":"_acme","type":"TXT","records":["\"token\""],"ttl":3600}]"#,
+                )
+            }
+        });
+        let records = backend(&url).get_zone_records().unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].header("authorization"), Some("Token secret"));
+        assert_eq!(records[0].name, "@");
+        assert_eq!(records[0].data, "2001:db8::1");
+        assert_eq!(records[1].data, "token");
+    }
+
+    #[test]
+    fn test_long_rate_limit_fails() {
+        let (url, handle) =
+            test_http::serve(1, |_| Response::new(429, "").header("Retry-After", "86400"));
+        let result = backend(&url).get_zone_records();
+        handle.join().unwrap();
+        assert_eq!(
+            result.unwrap_err().message,
+            "deSEC throttled the request and asks to wait 86400s before retrying, \
+             more than the 60s waited at most"
+        );
+    }
+
+    #[test]
+    fn test_update_record_puts_rrset() {
+        let (url, handle) = test_http::serve(1, |_| Response::new(200, "{}"));
+        let record = DnsRecord {
+            kind: DnsRecordType::AAAA,
+            name: "@".to_string(),
+            data: "2001:db8::1".to_string(),
+        };
+        backend(&url).update_record(&record, "2001:db8::2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/domains/example.dedyn.io/rrsets/@/AAAA/");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["subname"], "");
+        assert_eq!(body["records"][0], "2001:db8::2");
+        assert_eq!(body["ttl"], 3600);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::dns_providers::{DnsBackend, DnsBackendError, DnsRecord};
+use crate::dns_providers::{normalize_name, DnsBackend, DnsBackendError, DnsRecord};
 use digitalocean::api::{Domain, DomainRecord};
 use digitalocean::request::Executable;
 use digitalocean::DigitalOcean;
@@ -22,6 +22,15 @@ impl DigitalOceanBackend {
         }
     }
 
+    /// Digital Ocean names are relative with `@` for the apex, but normalize them anyway
+    fn to_record(&self, value: &DomainRecord) -> DnsRecord {
+        DnsRecord {
+            kind: value.kind().as_str().into(),
+            name: normalize_name(value.name(), &self.zone),
+            data: value.data().to_string(),
+        }
+    }
+
     fn _get_records_internal(&self) -> Result<Vec<DomainRecord>, DnsBackendError> {
         match Domain::get(&self.zone).records().execute(&self.client) {
             Ok(records) => {
@@ -45,7 +54,7 @@ impl DnsBackend for DigitalOceanBackend {
     }
     fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
         let records = &self._get_records_internal()?;
-        Ok(records.iter().map(|x| DnsRecord::from(x.clone())).collect())
+        Ok(records.iter().map(|x| self.to_record(x)).collect())
     }
 
     fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
@@ -61,16 +70,13 @@ impl DnsBackend for DigitalOceanBackend {
         }
     }
 
-    #[allow(suspicious_double_ref_op)]
     fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
         let current_records = self._get_records_internal()?;
         let existing_record = current_records
             .iter()
             .find(|x| {
-                // Intentionally cloning the reference here, because closures are weird
-                let local_record = x.clone();
-                let conv = DnsRecord::from(local_record.clone());
-                conv.name() == record.name && conv.kind == record.kind
+                let conv = self.to_record(x);
+                conv.name == record.name && conv.kind == record.kind && conv.data == record.data
             })
             .ok_or(DnsBackendError {
                 message: "Tried to update a nonexistant record".to_string(),
@@ -91,13 +97,3 @@ impl DnsBackend for DigitalOceanBackend {
         }
     }
 }
-
-impl From<DomainRecord> for DnsRecord {
-    fn from(value: DomainRecord) -> Self {
-        DnsRecord {
-            kind: value.kind().as_str().into(),
-            name: value.name().to_string(),
-            data: value.data().to_string(),
-        }
-    }
-}
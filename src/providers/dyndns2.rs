@@ -0,0 +1,291 @@
+use crate::dns_providers::{fqdn, DnsBackend, DnsBackendError, DnsRecord};
+use crate::record_cache::RecordCache;
+use reqwest::blocking::Client;
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+const DEFAULT_CACHE_FILE: &str = "/var/cache/dns-agent/dyndns2.toml";
+
+/// Well known services speaking the dyndns2 protocol
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Dyndns2Provider {
+    NoIp,
+    Dynu,
+    Dynv6,
+    /// Hurricane Electric, use the record's FQDN as username and its DDNS key as password
+    HeNet,
+    DynDns,
+}
+
+impl Dyndns2Provider {
+    fn update_url(&self) -> &'static str {
+        match self {
+            Dyndns2Provider::NoIp => "https://dynupdate.no-ip.com/nic/update",
+            Dyndns2Provider::Dynu => "https://api.dynu.com/nic/update",
+            Dyndns2Provider::Dynv6 => "https://dynv6.com/nic/update",
+            Dyndns2Provider::HeNet => "https://dyn.dns.he.net/nic/update",
+            Dyndns2Provider::DynDns => "https://members.dyndns.org/nic/update",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Dyndns2BackendConfig {
+    pub provider: Option<Dyndns2Provider>,
+    /// Full update URL (e.g. `https://example.net/nic/update`), overrides `provider`
+    pub server: Option<String>,
+    pub username: String,
+    pub password: String,
+    /// Where published addresses are remembered, since dyndns2 can't list records
+    pub cache_file: Option<String>,
+}
+
+/// Failure responses defined by the dyndns2 protocol
+#[derive(Debug, PartialEq)]
+pub enum Dyndns2Error {
+    BadAuth,
+    NotDonator,
+    NotFqdn,
+    NoHost,
+    NumHost,
+    Abuse,
+    BadAgent,
+    DnsError,
+    ServerError,
+    Unknown(String),
+}
+
+impl fmt::Display for Dyndns2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dyndns2Error::BadAuth => write!(f, "badauth: invalid username or password"),
+            Dyndns2Error::NotDonator => write!(f, "!donator: feature requires a paid account"),
+            Dyndns2Error::NotFqdn => write!(f, "notfqdn: hostname is not a fully qualified name"),
+            Dyndns2Error::NoHost => write!(f, "nohost: hostname does not exist in this account"),
+            Dyndns2Error::NumHost => write!(f, "numhost: too many hosts in one request"),
+            Dyndns2Error::Abuse => write!(f, "abuse: hostname is blocked for update abuse"),
+            Dyndns2Error::BadAgent => write!(f, "badagent: user agent was rejected"),
+            Dyndns2Error::DnsError => write!(f, "dnserr: provider side DNS error"),
+            Dyndns2Error::ServerError => write!(f, "911: provider is having problems"),
+            Dyndns2Error::Unknown(response) => write!(f, "unexpected response: {response}"),
+        }
+    }
+}
+
+impl From<Dyndns2Error> for DnsBackendError {
+    fn from(e: Dyndns2Error) -> Self {
+        DnsBackendError {
+            message: format!("dyndns2 update failed: {e}"),
+        }
+    }
+}
+
+/// Interpret the body of a `/nic/update` response
+pub fn parse_response(body: &str) -> Result<(), Dyndns2Error> {
+    let body = body.trim();
+    let code = body.split_whitespace().next().unwrap_or("");
+    match code {
+        "good" | "nochg" => Ok(()),
+        "badauth" => Err(Dyndns2Error::BadAuth),
+        "!donator" => Err(Dyndns2Error::NotDonator),
+        "notfqdn" => Err(Dyndns2Error::NotFqdn),
+        "nohost" | "!yours" => Err(Dyndns2Error::NoHost),
+        "numhost" => Err(Dyndns2Error::NumHost),
+        "abuse" => Err(Dyndns2Error::Abuse),
+        "badagent" => Err(Dyndns2Error::BadAgent),
+        "dnserr" => Err(Dyndns2Error::DnsError),
+        "911" => Err(Dyndns2Error::ServerError),
+        _ => Err(Dyndns2Error::Unknown(body.to_string())),
+    }
+}
+
+pub struct Dyndns2Backend {
+    client: Client,
+    update_url: String,
+    username: String,
+    password: String,
+    zone: String,
+    cache: RecordCache,
+}
+
+impl Dyndns2Backend {
+    pub fn new(config: Dyndns2BackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let update_url = match (config.server, config.provider) {
+            (Some(server), _) => server,
+            (None, Some(provider)) => provider.update_url().to_string(),
+            (None, None) => {
+                return Err(DnsBackendError {
+                    message: format!(
+                        "dyndns2 backend for {zone} needs either a provider or a server"
+                    ),
+                })
+            }
+        };
+        let client = Client::builder()
+            .user_agent(concat!("dns-agent/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let cache = RecordCache::new(
+            config
+                .cache_file
+                .unwrap_or_else(|| DEFAULT_CACHE_FILE.to_string()),
+        );
+        // Without a cache every run would send an update, which providers treat as abuse
+        cache.check_writable()?;
+        Ok(Self {
+            client,
+            update_url,
+            username: config.username,
+            password: config.password,
+            zone,
+            cache,
+        })
+    }
+
+    fn send_update(&self, record: &DnsRecord, address: &str) -> Result<(), DnsBackendError> {
+        let hostname = fqdn(&record.name, &self.zone);
+        let response = self
+            .client
+            .get(&self.update_url)
+            .basic_auth(&self.username, Some(&self.password))
+            .query(&[("hostname", hostname.as_str()), ("myip", address)])
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        let body = response.text().unwrap_or_default();
+        debug!("dyndns2 response for {hostname}: {status} {body}");
+        // Most services answer with 200 for every outcome, but some use 401 for badauth
+        parse_response(&body)?;
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!("dyndns2 server returned {status}: {body}"),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl DnsBackend for Dyndns2Backend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Err(DnsBackendError {
+            message: "dyndns2 can't list zone records".to_string(),
+        })
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self.send_update(&record, &record.data)
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        self.send_update(record, new_data)
+    }
+
+    fn record_cache(&self) -> Option<&RecordCache> {
+        Some(&self.cache)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ParsedRecord;
+    use crate::dns_providers::{update_records, DnsRecordType};
+    use crate::test_http::{self, Response};
+    use crate::update::{SystemAddresses, SystemV4Address};
+    use std::path::{Path, PathBuf};
+
+    fn config(server: &str, cache_file: &Path) -> Dyndns2BackendConfig {
+        Dyndns2BackendConfig {
+            provider: None,
+            server: Some(format!("{server}/nic/update")),
+            username: "user".to_string(),
+            password: "pass".to_string(),
+            cache_file: Some(cache_file.display().to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(parse_response("good 192.0.2.1"), Ok(()));
+        assert_eq!(parse_response("nochg 192.0.2.1\n"), Ok(()));
+        assert_eq!(parse_response("badauth"), Err(Dyndns2Error::BadAuth));
+        assert_eq!(parse_response("abuse"), Err(Dyndns2Error::Abuse));
+        assert_eq!(
+            parse_response("<html>"),
+            Err(Dyndns2Error::Unknown("<html>".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_update_uses_cache() {
+        let dir = std::env::temp_dir().join(format!("dns-agent-dyndns2-{}", std::process::id()));
+        let cache_file = dir.join("cache.toml");
+        RecordCache::new(&cache_file)
+            .store(
+                "example.com",
+                &DnsRecord {
+                    kind: DnsRecordType::A,
+                    name: "home".to_string(),
+                    data: "192.0.2.1".to_string(),
+                },
+            )
+            .unwrap();
+        let (url, handle) = test_http::serve(1, |_| Response::new(200, "good 192.0.2.2"));
+        let interfaces = SystemAddresses {
+            v4_addresses: vec![SystemV4Address {
+                interface: "eth0".to_string(),
+                address: "192.0.2.2".parse().unwrap(),
+            }],
+            v6_addresses: Vec::new(),
+        };
+        let desired = || {
+            vec![ParsedRecord {
+                name: "home".to_string(),
+                record_type: DnsRecordType::A,
+                interface: "eth0".to_string(),
+            }]
+        };
+        // get_zone_records always fails, so this only succeeds by reading the cache
+        let backend = Dyndns2Backend::new(config(&url, &cache_file), "example.com".to_string());
+        update_records(backend.unwrap(), desired(), &interfaces).unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(
+            requests[0].path,
+            "/nic/update?hostname=home.example.com&myip=192.0.2.2"
+        );
+        // "user:pass"
+        assert_eq!(
+            requests[0].header("Authorization"),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        // Nothing changed since, so no request is sent and no server is needed
+        let backend = Dyndns2Backend::new(config(&url, &cache_file), "example.com".to_string());
+        update_records(backend.unwrap(), desired(), &interfaces).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_unwritable_cache() {
+        let cache_file = PathBuf::from("/dev/null/dyndns2.toml");
+        let error = Dyndns2Backend::new(
+            config("http://127.0.0.1:9", &cache_file),
+            "example.com".to_string(),
+        )
+        .err()
+        .unwrap();
+        assert!(
+            error.message.contains("is not writable"),
+            "{}",
+            error.message
+        );
+    }
+}
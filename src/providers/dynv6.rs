@@ -0,0 +1,316 @@
+use crate::dns_providers::{normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://dynv6.com/api/v2";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Dynv6BackendConfig {
+    /// HTTP token from the dynv6 account's Keys page
+    pub token: String,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+/// The zone's apex addresses are properties of the zone itself rather than records
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Zone {
+    id: u64,
+    name: String,
+    ipv4address: Option<String>,
+    ipv6prefix: Option<String>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct ZoneUpdate<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv4address: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv6prefix: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Dynv6Record {
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+    data: String,
+}
+
+#[derive(Serialize, Debug)]
+struct RecordRequest<'a> {
+    #[serde(rename = "type")]
+    kind: String,
+    name: &'a str,
+    data: &'a str,
+}
+
+pub struct Dynv6Backend {
+    client: Client,
+    endpoint: String,
+    token: String,
+    zone_id: u64,
+    zone: String,
+}
+
+impl Dynv6Backend {
+    /// Create the backend, looking up the dynv6 zone ID for `zone`
+    pub fn new(config: Dynv6BackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let mut backend = Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            token: config.token,
+            zone_id: 0,
+            zone: zone.trim_end_matches('.').to_string(),
+        };
+        let zone = backend._get_zone()?;
+        debug!("Resolved dynv6 zone {} to ID {}", zone.name, zone.id);
+        backend.zone_id = zone.id;
+        Ok(backend)
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let response = request
+            .bearer_auth(&self.token)
+            .header("Accept", "application/json")
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "dynv6 API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse dynv6 API response: {e:?}"),
+        })
+    }
+
+    fn _get_zone(&self) -> Result<Zone, DnsBackendError> {
+        let request = self
+            .client
+            .get(format!("{}/zones/by-name/{}", self.endpoint, self.zone));
+        self.send(request).map_err(|e| DnsBackendError {
+            message: format!("Zone {} not found in dynv6 account: {}", self.zone, e),
+        })
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<Dynv6Record>, DnsBackendError> {
+        let request = self
+            .client
+            .get(format!("{}/zones/{}/records", self.endpoint, self.zone_id));
+        let records: Vec<Dynv6Record> = self.send(request)?;
+        debug!(
+            "Fetched records from dynv6 for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+
+    /// Set the zone's own IPv4 address or IPv6 prefix, which serve the apex A/AAAA
+    fn update_zone_address(&self, record: &DnsRecord, data: &str) -> Result<(), DnsBackendError> {
+        let body = match record.kind {
+            DnsRecordType::A => ZoneUpdate {
+                ipv4address: Some(data),
+                ..Default::default()
+            },
+            DnsRecordType::AAAA => ZoneUpdate {
+                ipv6prefix: Some(data),
+                ..Default::default()
+            },
+            _ => {
+                return Err(DnsBackendError {
+                    message: "dynv6 only supports A and AAAA records at the zone apex".to_string(),
+                })
+            }
+        };
+        let request = self
+            .client
+            .patch(format!("{}/zones/{}", self.endpoint, self.zone_id))
+            .json(&body);
+        self.send::<serde::de::IgnoredAny>(request)?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for Dynv6Backend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let zone = self._get_zone()?;
+        let mut records = Vec::new();
+        if let Some(address) = zone.ipv4address.filter(|x| !x.is_empty()) {
+            records.push(DnsRecord {
+                kind: DnsRecordType::A,
+                name: "@".to_string(),
+                data: address,
+            });
+        }
+        if let Some(prefix) = zone.ipv6prefix.filter(|x| !x.is_empty()) {
+            records.push(DnsRecord {
+                kind: DnsRecordType::AAAA,
+                name: "@".to_string(),
+                // Reported with a prefix length when set to a network rather than a host
+                data: prefix.split('/').next().unwrap_or_default().to_string(),
+            });
+        }
+        records.extend(
+            self._get_records_internal()?
+                .into_iter()
+                .map(|x| DnsRecord {
+                    kind: x.kind.as_str().into(),
+                    name: normalize_name(&x.name, &self.zone),
+                    data: x.data,
+                }),
+        );
+        Ok(records)
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let result = if record.name == "@" {
+            self.update_zone_address(&record, &record.data)
+        } else {
+            let body = RecordRequest {
+                kind: record.kind.clone().into(),
+                name: &record.name,
+                data: &record.data,
+            };
+            let request = self
+                .client
+                .post(format!("{}/zones/{}/records", self.endpoint, self.zone_id))
+                .json(&body);
+            self.send::<serde::de::IgnoredAny>(request).map(|_| ())
+        };
+        result.map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        if record.name == "@" {
+            return self
+                .update_zone_address(record, new_data)
+                .map_err(|e| DnsBackendError {
+                    message: format!(
+                        "Failed to update DNS record {record:?} with value {new_data}: {e}"
+                    ),
+                });
+        }
+        let current_records = self._get_records_internal()?;
+        let existing_record = current_records
+            .into_iter()
+            .find(|x| {
+                normalize_name(&x.name, &self.zone) == record.name
+                    && DnsRecordType::from(x.kind.as_str()) == record.kind
+                    && x.data == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let body = RecordRequest {
+            kind: record.kind.clone().into(),
+            name: &record.name,
+            data: new_data,
+        };
+        let request = self
+            .client
+            .patch(format!(
+                "{}/zones/{}/records/{}",
+                self.endpoint, self.zone_id, existing_record.id
+            ))
+            .json(&body);
+        self.send::<serde::de::IgnoredAny>(request)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {existing_record:?} with value {new_data}: {e}"
+                ),
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    const ZONE: &str =
+        r#"{"id":42,"name":"home.dynv6.net","ipv4address":"","ipv6prefix":"2001:db8:1::1"}"#;
+
+    #[test]
+    fn test_apex_addresses_are_zone_properties() {
+        let (url, handle) = test_http::serve(4, |request| match request.path.as_str() {
+            "/zones/by-name/home.dynv6.net" => Response::new(200, ZONE),
+            "/zones/42/records" => Response::new(
+                200,
+                r#"[{"id":7,"type":"AAAA","name":"nas","data":"2001:db8:1::10","zoneID":42}]"#,
+            ),
+            _ => Response::new(200, ZONE),
+        });
+        let config = Dynv6BackendConfig {
+            token: "token".to_string(),
+            endpoint: Some(url),
+        };
+        let backend = Dynv6Backend::new(config, "home.dynv6.net".to_string()).unwrap();
+        let records = backend.get_zone_records().unwrap();
+        backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::A,
+                name: "@".to_string(),
+                data: "192.0.2.1".to_string(),
+            })
+            .unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "@");
+        assert_eq!(records[0].kind, DnsRecordType::AAAA);
+        assert_eq!(records[0].data, "2001:db8:1::1");
+        assert_eq!(records[1].name, "nas");
+        assert_eq!(requests[3].method, "PATCH");
+        assert_eq!(requests[3].path, "/zones/42");
+        assert_eq!(requests[3].body, r#"{"ipv4address":"192.0.2.1"}"#);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer token"));
+    }
+
+    #[test]
+    fn test_record_names_are_normalized() {
+        let (url, handle) = test_http::serve(5, |request| match request.path.as_str() {
+            "/zones/42/records" => Response::new(
+                200,
+                r#"[{"id":7,"type":"A","name":"nas.home.dynv6.net.","data":"192.0.2.10","zoneID":42}]"#,
+            ),
+            "/zones/42/records/7" => Response::new(200, "{}"),
+            _ => Response::new(200, ZONE),
+        });
+        let config = Dynv6BackendConfig {
+            token: "token".to_string(),
+            endpoint: Some(url),
+        };
+        let backend = Dynv6Backend::new(config, "home.dynv6.net".to_string()).unwrap();
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records[1].name, "nas");
+        backend.update_record(&records[1], "192.0.2.11").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[4].method, "PATCH");
+        assert_eq!(requests[4].path, "/zones/42/records/7");
+        assert_eq!(
+            requests[4].body,
+            r#"{"type":"A","name":"nas","data":"192.0.2.11"}"#
+        );
+    }
+}
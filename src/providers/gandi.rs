@@ -0,0 +1,275 @@
+use crate::dns_providers::{DnsBackend, DnsBackendError, DnsRecord, DnsRecordType};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.gandi.net/v5/livedns";
+const DEFAULT_TTL: u32 = 10800;
+const PER_PAGE: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GandiBackendConfig {
+    /// Personal access token with the "Manage domain name technical configurations" permission
+    pub personal_access_token: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RRSet {
+    rrset_name: String,
+    rrset_type: String,
+    rrset_values: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct RRSetUpdate {
+    rrset_values: Vec<String>,
+    rrset_ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    message: Option<String>,
+}
+
+pub struct GandiBackend {
+    client: Client,
+    endpoint: String,
+    token: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl GandiBackend {
+    pub fn new(config: GandiBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            token: config.personal_access_token,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<Response, DnsBackendError> {
+        let response = request
+            .bearer_auth(&self.token)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&text)
+                .ok()
+                .and_then(|x| x.message)
+                .unwrap_or(text);
+            return Err(DnsBackendError {
+                message: format!("Gandi API returned {status}: {message}"),
+            });
+        }
+        Ok(response)
+    }
+
+    fn _get_rrsets_internal(&self) -> Result<Vec<RRSet>, DnsBackendError> {
+        let mut rrsets = Vec::new();
+        let mut page = 1;
+        loop {
+            let request = self
+                .client
+                .get(format!("{}/domains/{}/records", self.endpoint, self.zone))
+                .query(&[("page", page), ("per_page", PER_PAGE)]);
+            let response = self.send(request)?;
+            let total: Option<usize> = response
+                .headers()
+                .get("total-count")
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse().ok());
+            let page_rrsets: Vec<RRSet> = response.json().map_err(|e| DnsBackendError {
+                message: format!("Could not parse Gandi API response: {e:?}"),
+            })?;
+            let page_len = page_rrsets.len();
+            rrsets.extend(page_rrsets);
+            match total {
+                Some(total) if rrsets.len() < total && page_len > 0 => page += 1,
+                _ => break,
+            }
+        }
+        debug!(
+            "Fetched rrsets from gandi for domain {}: {:?}",
+            &self.zone, rrsets
+        );
+        Ok(rrsets)
+    }
+
+    /// Replace the `rrset_values` of the record's name and type in a single request
+    fn replace_rrset(&self, record: &DnsRecord, data: &str) -> Result<(), DnsBackendError> {
+        let value = match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => data.to_string(),
+            DnsRecordType::TXT => format!("\"{}\"", data.replace('"', "\\\"")),
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Record type not supported".to_string(),
+                })
+            }
+        };
+        let kind: String = record.kind.clone().into();
+        let request = self
+            .client
+            .put(format!(
+                "{}/domains/{}/records/{}/{}",
+                self.endpoint, self.zone, record.name, kind
+            ))
+            .json(&RRSetUpdate {
+                rrset_values: vec![value],
+                rrset_ttl: self.ttl,
+            });
+        self.send(request)?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for GandiBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let rrsets = self._get_rrsets_internal()?;
+        let mut records = Vec::new();
+        for rrset in rrsets {
+            let kind: DnsRecordType = rrset.rrset_type.as_str().into();
+            for value in rrset.rrset_values {
+                let data = match kind {
+                    DnsRecordType::TXT => value
+                        .strip_prefix('"')
+                        .and_then(|x| x.strip_suffix('"'))
+                        .unwrap_or(&value)
+                        .replace("\\\"", "\""),
+                    _ => value,
+                };
+                records.push(DnsRecord {
+                    kind: kind.clone(),
+                    name: rrset.rrset_name.clone(),
+                    data,
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self.replace_rrset(&record, &record.data)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        self.replace_rrset(record, new_data)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {record:?} with value {new_data}: {e}"
+                ),
+            })
+    }
+
+    fn updates_whole_rrset(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn backend(url: &str) -> GandiBackend {
+        let config = GandiBackendConfig {
+            personal_access_token: "pat".to_string(),
+            ttl: None,
+            endpoint: Some(url.to_string()),
+        };
+        GandiBackend::new(config, "example.com".to_string())
+    }
+
+    #[test]
+    fn test_get_zone_records() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.path.contains("page=1&") {
+                Response::new(
+                    200,
+                    r#"[{"rrset_name":"@","rrset_type":"A","rrset_ttl":10800,"rrset_values":["192.0.2.1","192.0.2.2"]}]"#,
+                )
+                .header("Total-Count", "2")
+            } else {
+                Response::new(
+                    200,
+                    r#"[{"rrset_name":"www","rrset_type":"TXT","rrset_ttl":10800,"rrset_values":["\"v=spf1 \\\"x\\\"\""]}]"#,
+                )
+                .header("Total-Count", "2")
+            }
+        });
+        let records = backend(&url).get_zone_records().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].data, "192.0.2.2");
+        assert_eq!(records[2].kind, DnsRecordType::TXT);
+        assert_eq!(records[2].data, "v=spf1 \"x\"");
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer pat"));
+        assert_eq!(
+            requests[0].path,
+            "/domains/example.com/records?page=1&per_page=500"
+        );
+        assert_eq!(
+            requests[1].path,
+            "/domains/example.com/records?page=2&per_page=500"
+        );
+    }
+
+    #[test]
+    fn test_rrset_put() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.path.ends_with("/TXT") {
+                Response::new(
+                    400,
+                    r#"{"code":400,"message":"Invalid value","object":"x"}"#,
+                )
+            } else {
+                Response::new(201, r#"{"message":"DNS Record Created"}"#)
+            }
+        });
+        let backend = backend(&url);
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend.update_record(&record, "192.0.2.9").unwrap();
+        let error = backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::TXT,
+                name: "www".to_string(),
+                data: "x".to_string(),
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Failed to create DNS record www: Gandi API returned 400 Bad Request: Invalid value"
+        );
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/domains/example.com/records/www/A");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["rrset_values"], serde_json::json!(["192.0.2.9"]));
+        assert_eq!(body["rrset_ttl"], DEFAULT_TTL);
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(body["rrset_values"], serde_json::json!(["\"x\""]));
+    }
+}
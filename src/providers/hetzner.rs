@@ -0,0 +1,321 @@
+use crate::dns_providers::{DnsBackend, DnsBackendError, DnsRecord, DnsRecordType};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://dns.hetzner.com/api/v1";
+const DEFAULT_TTL: u32 = 3600;
+const PER_PAGE: u32 = 100;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HetznerBackendConfig {
+    pub api_token: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ZonesResponse {
+    zones: Vec<Zone>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct HetznerRecord {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+    value: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pagination {
+    last_page: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct Meta {
+    pagination: Option<Pagination>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordsResponse {
+    records: Vec<HetznerRecord>,
+    meta: Option<Meta>,
+}
+
+#[derive(Serialize, Debug)]
+struct RecordRequest<'a> {
+    zone_id: &'a str,
+    #[serde(rename = "type")]
+    kind: String,
+    name: &'a str,
+    value: &'a str,
+    ttl: u32,
+}
+
+impl From<HetznerRecord> for DnsRecord {
+    fn from(value: HetznerRecord) -> Self {
+        DnsRecord {
+            kind: value.kind.as_str().into(),
+            name: value.name,
+            data: value.value,
+        }
+    }
+}
+
+pub struct HetznerBackend {
+    client: Client,
+    endpoint: String,
+    api_token: String,
+    zone_id: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl HetznerBackend {
+    /// Create the backend, looking up the Hetzner zone ID for `zone`
+    pub fn new(config: HetznerBackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let mut backend = Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_token: config.api_token,
+            zone_id: String::new(),
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        };
+        backend.zone_id = backend._lookup_zone_id()?;
+        Ok(backend)
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let response = request
+            .header("Auth-API-Token", &self.api_token)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Hetzner API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Hetzner API response: {e:?}"),
+        })
+    }
+
+    fn _lookup_zone_id(&self) -> Result<String, DnsBackendError> {
+        let request = self
+            .client
+            .get(format!("{}/zones", self.endpoint))
+            .query(&[("name", &self.zone)]);
+        let response: ZonesResponse = self.send(request)?;
+        let zone = response
+            .zones
+            .into_iter()
+            .find(|x| x.name.eq_ignore_ascii_case(&self.zone))
+            .ok_or(DnsBackendError {
+                message: format!("Zone {} not found in Hetzner DNS account", self.zone),
+            })?;
+        debug!("Resolved hetzner zone {} to ID {}", self.zone, zone.id);
+        Ok(zone.id)
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<HetznerRecord>, DnsBackendError> {
+        let mut records = Vec::new();
+        let mut page = 1;
+        loop {
+            let request = self
+                .client
+                .get(format!("{}/records", self.endpoint))
+                .query(&[
+                    ("zone_id", self.zone_id.clone()),
+                    ("page", page.to_string()),
+                    ("per_page", PER_PAGE.to_string()),
+                ]);
+            let response: RecordsResponse = self.send(request)?;
+            records.extend(response.records);
+            let last_page = response
+                .meta
+                .and_then(|x| x.pagination)
+                .map(|x| x.last_page)
+                .unwrap_or(page);
+            if page >= last_page {
+                break;
+            }
+            page += 1;
+        }
+        debug!(
+            "Fetched records from hetzner for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+}
+
+impl DnsBackend for HetznerBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let records = self._get_records_internal()?;
+        Ok(records.into_iter().map(DnsRecord::from).collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let body = RecordRequest {
+            zone_id: &self.zone_id,
+            kind: record.kind.clone().into(),
+            name: &record.name,
+            value: &record.data,
+            ttl: self.ttl,
+        };
+        let request = self
+            .client
+            .post(format!("{}/records", self.endpoint))
+            .json(&body);
+        self.send::<serde::de::IgnoredAny>(request)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let current_records = self._get_records_internal()?;
+        let existing_record = current_records
+            .into_iter()
+            .find(|x| {
+                x.name == record.name
+                    && DnsRecordType::from(x.kind.as_str()) == record.kind
+                    && x.value == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let body = RecordRequest {
+            zone_id: &self.zone_id,
+            kind: record.kind.clone().into(),
+            name: &record.name,
+            value: new_data,
+            ttl: self.ttl,
+        };
+        let request = self
+            .client
+            .put(format!("{}/records/{}", self.endpoint, existing_record.id))
+            .json(&body);
+        self.send::<serde::de::IgnoredAny>(request)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {existing_record:?} with value {new_data}: {e}"
+                ),
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Request, Response};
+
+    const ZONES: &str = r#"{"zones":[{"id":"other","name":"example.community"},{"id":"zone1","name":"example.com"}]}"#;
+
+    fn backend(url: &str) -> HetznerBackend {
+        let config = HetznerBackendConfig {
+            api_token: "token".to_string(),
+            ttl: None,
+            endpoint: Some(url.to_string()),
+        };
+        HetznerBackend::new(config, "example.com.".to_string()).unwrap()
+    }
+
+    /// Two pages of records, with two A records for `www`
+    fn records(request: &Request) -> Response {
+        if request.path.contains("&page=1&") {
+            Response::new(
+                200,
+                r#"{"records":[{"id":"r1","type":"A","name":"www","value":"192.0.2.1"}],"meta":{"pagination":{"page":1,"per_page":100,"last_page":2}}}"#,
+            )
+        } else {
+            Response::new(
+                200,
+                r#"{"records":[{"id":"r2","type":"A","name":"www","value":"192.0.2.2"}],"meta":{"pagination":{"page":2,"per_page":100,"last_page":2}}}"#,
+            )
+        }
+    }
+
+    #[test]
+    fn test_zone_lookup_and_listing() {
+        let (url, handle) = test_http::serve(3, |request| match request.path.as_str() {
+            x if x.starts_with("/zones") => Response::new(200, ZONES),
+            _ => records(request),
+        });
+        let records = backend(&url).get_zone_records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].data, "192.0.2.2");
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].path, "/zones?name=example.com");
+        assert_eq!(requests[0].header("Auth-API-Token"), Some("token"));
+        assert_eq!(
+            requests[1].path,
+            "/records?zone_id=zone1&page=1&per_page=100"
+        );
+        assert_eq!(
+            requests[2].path,
+            "/records?zone_id=zone1&page=2&per_page=100"
+        );
+    }
+
+    #[test]
+    fn test_create_and_update() {
+        let (url, handle) = test_http::serve(5, |request| match request.path.as_str() {
+            x if x.starts_with("/zones") => Response::new(200, ZONES),
+            x if x.starts_with("/records?") => records(request),
+            _ => Response::new(200, r#"{"record":{}}"#),
+        });
+        let backend = backend(&url);
+        backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::AAAA,
+                name: "www".to_string(),
+                data: "2001:db8::1".to_string(),
+            })
+            .unwrap();
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.2".to_string(),
+        };
+        backend.update_record(&record, "192.0.2.3").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[1].method, "POST");
+        assert_eq!(requests[1].path, "/records");
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(body["zone_id"], "zone1");
+        assert_eq!(body["type"], "AAAA");
+        assert_eq!(body["ttl"], DEFAULT_TTL);
+        // The second www record is the one holding the old value
+        assert_eq!(requests[4].method, "PUT");
+        assert_eq!(requests[4].path, "/records/r2");
+        let body: serde_json::Value = serde_json::from_str(&requests[4].body).unwrap();
+        assert_eq!(body["value"], "192.0.2.3");
+    }
+}
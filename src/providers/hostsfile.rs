@@ -0,0 +1,332 @@
+use super::{run_reload_command, write_atomic};
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostsFileFormat {
+    /// `address name` lines, for `/etc/hosts` or a dnsmasq `addn-hosts` file
+    Hosts,
+    /// `host-record=name,address` lines for a dnsmasq conf-dir file
+    DnsmasqHostRecord,
+    /// `local-data: "name TTL IN TYPE address"` lines for an Unbound include
+    UnboundLocalData,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HostsFileBackendConfig {
+    /// File containing the managed block, defaults to `/etc/hosts`
+    pub path: Option<String>,
+    /// Defaults to `hosts`
+    pub format: Option<HostsFileFormat>,
+    /// Command run after the file changes, e.g. `["unbound-control", "reload"]`
+    pub reload_command: Option<Vec<String>>,
+    /// TTL for Unbound local-data records
+    pub ttl: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct HostEntry {
+    name: String,
+    address: IpAddr,
+}
+
+/// The file split around the block of lines dns-agent owns
+struct ManagedFile {
+    before: String,
+    entries: Vec<HostEntry>,
+    after: String,
+}
+
+pub struct HostsFileBackend {
+    path: PathBuf,
+    format: HostsFileFormat,
+    reload_command: Option<Vec<String>>,
+    ttl: u32,
+    zone: String,
+}
+
+impl HostsFileBackend {
+    pub fn new(config: HostsFileBackendConfig, zone: String) -> Self {
+        Self {
+            path: PathBuf::from(config.path.unwrap_or_else(|| "/etc/hosts".to_string())),
+            format: config.format.unwrap_or(HostsFileFormat::Hosts),
+            reload_command: config.reload_command,
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+            zone: zone.trim_end_matches('.').to_string(),
+        }
+    }
+
+    fn begin_marker(&self) -> String {
+        format!("# BEGIN dns-agent {}", self.zone)
+    }
+
+    fn end_marker(&self) -> String {
+        format!("# END dns-agent {}", self.zone)
+    }
+
+    fn parse_line(&self, line: &str) -> Vec<HostEntry> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let entry = |name: &str, address: &str| {
+            address.parse().ok().map(|address| HostEntry {
+                name: name.trim_end_matches('.').to_string(),
+                address,
+            })
+        };
+        match self.format {
+            HostsFileFormat::Hosts => {
+                let mut fields = line.split_whitespace();
+                match fields.next() {
+                    Some(address) => fields.filter_map(|name| entry(name, address)).collect(),
+                    None => Vec::new(),
+                }
+            }
+            HostsFileFormat::DnsmasqHostRecord => {
+                let values = match line.strip_prefix("host-record=") {
+                    Some(values) => values.split(',').map(str::trim).collect::<Vec<&str>>(),
+                    None => return Vec::new(),
+                };
+                let (addresses, names): (Vec<&str>, Vec<&str>) = values
+                    .into_iter()
+                    .filter(|x| x.parse::<u32>().is_err())
+                    .partition(|x| x.parse::<IpAddr>().is_ok());
+                names
+                    .iter()
+                    .flat_map(|name| addresses.iter().filter_map(|address| entry(name, address)))
+                    .collect()
+            }
+            HostsFileFormat::UnboundLocalData => {
+                let data = match line.strip_prefix("local-data:") {
+                    Some(data) => data.trim().trim_matches('"'),
+                    None => return Vec::new(),
+                };
+                let fields: Vec<&str> = data.split_whitespace().collect();
+                match (fields.first(), fields.last()) {
+                    (Some(name), Some(address)) => entry(name, address).into_iter().collect(),
+                    _ => Vec::new(),
+                }
+            }
+        }
+    }
+
+    fn render_line(&self, entry: &HostEntry) -> String {
+        match self.format {
+            HostsFileFormat::Hosts => format!("{}\t{}", entry.address, entry.name),
+            HostsFileFormat::DnsmasqHostRecord => {
+                format!("host-record={},{}", entry.name, entry.address)
+            }
+            HostsFileFormat::UnboundLocalData => {
+                let kind = if entry.address.is_ipv4() { "A" } else { "AAAA" };
+                format!(
+                    "local-data: \"{}. {} IN {} {}\"",
+                    entry.name, self.ttl, kind, entry.address
+                )
+            }
+        }
+    }
+
+    fn read(&self) -> Result<ManagedFile, DnsBackendError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(DnsBackendError {
+                    message: format!("Failed to read {}: {}", self.path.display(), e),
+                })
+            }
+        };
+        let begin = self.begin_marker();
+        let end = self.end_marker();
+        let lines: Vec<&str> = contents.split_inclusive('\n').collect();
+        let begin_index = lines.iter().position(|x| x.trim_end() == begin);
+        let end_index = lines.iter().position(|x| x.trim_end() == end);
+        match (begin_index, end_index) {
+            (None, None) => Ok(ManagedFile {
+                before: contents,
+                entries: Vec::new(),
+                after: String::new(),
+            }),
+            (Some(begin_index), Some(end_index)) if begin_index < end_index => Ok(ManagedFile {
+                before: lines[..begin_index].concat(),
+                entries: lines[begin_index + 1..end_index]
+                    .iter()
+                    .flat_map(|x| self.parse_line(x))
+                    .collect(),
+                after: lines[end_index + 1..].concat(),
+            }),
+            _ => Err(DnsBackendError {
+                message: format!(
+                    "Managed block for {} in {} is malformed, refusing to edit it",
+                    self.zone,
+                    self.path.display()
+                ),
+            }),
+        }
+    }
+
+    fn write(&self, file: ManagedFile) -> Result<(), DnsBackendError> {
+        let mut contents = file.before;
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        contents.push_str(&self.begin_marker());
+        contents.push('\n');
+        for entry in &file.entries {
+            contents.push_str(&self.render_line(entry));
+            contents.push('\n');
+        }
+        contents.push_str(&self.end_marker());
+        contents.push('\n');
+        contents.push_str(&file.after);
+        write_atomic(&self.path, &contents).map_err(|e| DnsBackendError {
+            message: format!("Failed to write {}: {}", self.path.display(), e),
+        })?;
+        if let Some(command) = &self.reload_command {
+            run_reload_command(command)?;
+        }
+        Ok(())
+    }
+
+    fn entry_for(&self, record: &DnsRecord, data: &str) -> Result<HostEntry, DnsBackendError> {
+        let address: IpAddr = match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => {
+                data.parse().map_err(|_| DnsBackendError {
+                    message: format!("Invalid address {data}"),
+                })?
+            }
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Record type not supported".to_string(),
+                })
+            }
+        };
+        Ok(HostEntry {
+            name: fqdn(&record.name, &self.zone),
+            address,
+        })
+    }
+}
+
+impl DnsBackend for HostsFileBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let file = self.read()?;
+        Ok(file
+            .entries
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: if x.address.is_ipv4() {
+                    DnsRecordType::A
+                } else {
+                    DnsRecordType::AAAA
+                },
+                name: relative_name(&x.name, &self.zone),
+                data: x.address.to_string(),
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let mut file = self.read()?;
+        file.entries.push(self.entry_for(&record, &record.data)?);
+        self.write(file)
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let mut file = self.read()?;
+        let old_entry = self.entry_for(record, &record.data)?;
+        let new_entry = self.entry_for(record, new_data)?;
+        let existing =
+            file.entries
+                .iter_mut()
+                .find(|x| **x == old_entry)
+                .ok_or(DnsBackendError {
+                    message: "Tried to update a nonexistant record".to_string(),
+                })?;
+        *existing = new_entry;
+        self.write(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend(name: &str, format: HostsFileFormat, contents: &str) -> (HostsFileBackend, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("dns-agent-hosts-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, contents).unwrap();
+        let config = HostsFileBackendConfig {
+            path: Some(path.to_string_lossy().to_string()),
+            format: Some(format),
+            reload_command: None,
+            ttl: Some(300),
+        };
+        (HostsFileBackend::new(config, "lan".to_string()), path)
+    }
+
+    #[test]
+    fn test_hosts_block_preserves_surroundings() {
+        let original = "127.0.0.1\tlocalhost\n# keep me\n";
+        let (backend, path) = backend("hosts", HostsFileFormat::Hosts, original);
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "nas".to_string(),
+            data: "192.168.1.10".to_string(),
+        };
+        backend.create_record(record.clone()).unwrap();
+        fs::write(
+            &path,
+            fs::read_to_string(&path).unwrap() + "10.0.0.1\tafter\n",
+        )
+        .unwrap();
+        backend.update_record(&record, "192.168.1.11").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1\tlocalhost\n# keep me\n# BEGIN dns-agent lan\n192.168.1.11\tnas.lan\n# END dns-agent lan\n10.0.0.1\tafter\n"
+        );
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "nas");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_unbound_and_dnsmasq_lines() {
+        let (unbound, path) = backend("unbound", HostsFileFormat::UnboundLocalData, "");
+        let entry = HostEntry {
+            name: "nas.lan".to_string(),
+            address: "2001:db8::10".parse().unwrap(),
+        };
+        let line = unbound.render_line(&entry);
+        assert_eq!(line, "local-data: \"nas.lan. 300 IN AAAA 2001:db8::10\"");
+        assert_eq!(unbound.parse_line(&line), vec![entry.clone()]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        let (dnsmasq, path) = backend("dnsmasq", HostsFileFormat::DnsmasqHostRecord, "");
+        assert_eq!(
+            dnsmasq
+                .parse_line("host-record=nas.lan,nas,192.168.1.10,2001:db8::10,600")
+                .len(),
+            4
+        );
+        assert_eq!(
+            dnsmasq.render_line(&entry),
+            "host-record=nas.lan,2001:db8::10"
+        );
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,307 @@
+use crate::dns_providers::{
+    apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.linode.com/v4";
+const DEFAULT_TTL: u32 = 3600;
+const PAGE_SIZE: u32 = 500;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LinodeBackendConfig {
+    /// Personal access token with the Domains read/write scope
+    pub api_token: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Page<T> {
+    data: Vec<T>,
+    page: u32,
+    pages: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct Domain {
+    id: u64,
+    domain: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct LinodeRecord {
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+    target: String,
+}
+
+#[derive(Serialize, Debug)]
+struct CreateRecordRequest<'a> {
+    #[serde(rename = "type")]
+    kind: String,
+    name: &'a str,
+    target: &'a str,
+    ttl_sec: u32,
+}
+
+#[derive(Serialize, Debug)]
+struct UpdateRecordRequest<'a> {
+    target: &'a str,
+    ttl_sec: u32,
+}
+
+impl LinodeRecord {
+    fn into_record(self, zone: &str) -> DnsRecord {
+        DnsRecord {
+            kind: self.kind.as_str().into(),
+            name: normalize_name(&self.name, zone),
+            data: self.target,
+        }
+    }
+}
+
+pub struct LinodeBackend {
+    client: Client,
+    endpoint: String,
+    api_token: String,
+    domain_id: u64,
+    zone: String,
+    ttl: u32,
+}
+
+impl LinodeBackend {
+    /// Create the backend, looking up the Linode domain ID for `zone`
+    pub fn new(config: LinodeBackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let mut backend = Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_token: config.api_token,
+            domain_id: 0,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        };
+        backend.domain_id = backend._lookup_domain_id()?;
+        Ok(backend)
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let response =
+            request
+                .bearer_auth(&self.api_token)
+                .send()
+                .map_err(|e| DnsBackendError {
+                    message: format!("Dns backend error: {e:?}"),
+                })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Linode API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Linode API response: {e:?}"),
+        })
+    }
+
+    /// Fetch every page of a collection endpoint
+    fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, DnsBackendError> {
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let request = self
+                .client
+                .get(format!("{}{path}", self.endpoint))
+                .query(&[("page", page), ("page_size", PAGE_SIZE)]);
+            let response: Page<T> = self.send(request)?;
+            items.extend(response.data);
+            if response.page >= response.pages {
+                break;
+            }
+            page += 1;
+        }
+        Ok(items)
+    }
+
+    fn _lookup_domain_id(&self) -> Result<u64, DnsBackendError> {
+        let domains: Vec<Domain> = self.get_all("/domains")?;
+        let domain = domains
+            .into_iter()
+            .find(|x| x.domain.eq_ignore_ascii_case(&self.zone))
+            .ok_or(DnsBackendError {
+                message: format!("Domain {} not found in Linode account", self.zone),
+            })?;
+        debug!("Resolved linode domain {} to ID {}", self.zone, domain.id);
+        Ok(domain.id)
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<LinodeRecord>, DnsBackendError> {
+        let records: Vec<LinodeRecord> =
+            self.get_all(&format!("/domains/{}/records", self.domain_id))?;
+        debug!(
+            "Fetched records from linode for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+}
+
+impl DnsBackend for LinodeBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let records = self._get_records_internal()?;
+        Ok(records
+            .into_iter()
+            .map(|x| x.into_record(&self.zone))
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let body = CreateRecordRequest {
+            kind: record.kind.clone().into(),
+            name: apex_as_empty(&record.name),
+            target: &record.data,
+            ttl_sec: self.ttl,
+        };
+        let request = self
+            .client
+            .post(format!(
+                "{}/domains/{}/records",
+                self.endpoint, self.domain_id
+            ))
+            .json(&body);
+        self.send::<serde::de::IgnoredAny>(request)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let current_records = self._get_records_internal()?;
+        let existing_record = current_records
+            .into_iter()
+            .find(|x| {
+                x.name == apex_as_empty(&record.name)
+                    && DnsRecordType::from(x.kind.as_str()) == record.kind
+                    && x.target == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let body = UpdateRecordRequest {
+            target: new_data,
+            ttl_sec: self.ttl,
+        };
+        let request = self
+            .client
+            .put(format!(
+                "{}/domains/{}/records/{}",
+                self.endpoint, self.domain_id, existing_record.id
+            ))
+            .json(&body);
+        self.send::<serde::de::IgnoredAny>(request)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {existing_record:?} with value {new_data}: {e}"
+                ),
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn backend(url: &str) -> LinodeBackend {
+        let config = LinodeBackendConfig {
+            api_token: "token".to_string(),
+            ttl: None,
+            endpoint: Some(url.to_string()),
+        };
+        LinodeBackend::new(config, "example.com.".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_pages_and_update() {
+        let (url, handle) = test_http::serve(5, |request| match request.path.as_str() {
+            "/domains?page=1&page_size=500" => Response::new(
+                200,
+                r#"{"data":[{"id":1,"domain":"example.org"}],"page":1,"pages":2,"results":2}"#,
+            ),
+            "/domains?page=2&page_size=500" => Response::new(
+                200,
+                r#"{"data":[{"id":42,"domain":"example.com"}],"page":2,"pages":2,"results":2}"#,
+            ),
+            "/domains/42/records?page=1&page_size=500" => Response::new(
+                200,
+                r#"{"data":[{"id":7,"type":"A","name":"","target":"192.0.2.1"}],"page":1,"pages":2,"results":2}"#,
+            ),
+            "/domains/42/records?page=2&page_size=500" => Response::new(
+                200,
+                r#"{"data":[{"id":8,"type":"A","name":"www","target":"192.0.2.1"}],"page":2,"pages":2,"results":2}"#,
+            ),
+            _ => Response::new(200, "{}"),
+        });
+        let backend = backend(&url);
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend.update_record(&record, "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer token"));
+        assert_eq!(requests[4].method, "PUT");
+        assert_eq!(requests[4].path, "/domains/42/records/8");
+        let body: serde_json::Value = serde_json::from_str(&requests[4].body).unwrap();
+        assert_eq!(body["target"], "192.0.2.2");
+        assert_eq!(body["ttl_sec"], DEFAULT_TTL);
+    }
+
+    #[test]
+    fn test_apex_records() {
+        let (url, handle) = test_http::serve(3, |request| match request.path.as_str() {
+            x if x.starts_with("/domains?") => Response::new(
+                200,
+                r#"{"data":[{"id":42,"domain":"example.com"}],"page":1,"pages":1,"results":1}"#,
+            ),
+            x if x.starts_with("/domains/42/records?") => Response::new(
+                200,
+                r#"{"data":[{"id":7,"type":"A","name":"","target":"192.0.2.1"}],"page":1,"pages":1,"results":1}"#,
+            ),
+            _ => Response::new(200, "{}"),
+        });
+        let backend = backend(&url);
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records[0].name, "@");
+        backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::AAAA,
+                name: "@".to_string(),
+                data: "2001:db8::1".to_string(),
+            })
+            .unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[2].path, "/domains/42/records");
+        let body: serde_json::Value = serde_json::from_str(&requests[2].body).unwrap();
+        assert_eq!(body["name"], "");
+        assert_eq!(body["type"], "AAAA");
+    }
+}
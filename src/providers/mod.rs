@@ -1,2 +1,129 @@
+pub mod adguard_home;
+pub mod azure_dns;
+pub mod bunny;
 pub mod cloudflare;
+pub mod desec;
 pub mod digitalocean;
+pub mod dyndns2;
+pub mod dynv6;
+pub mod gandi;
+pub mod gcloud_dns;
+pub mod hetzner;
+pub mod hostsfile;
+pub mod linode;
+pub mod namecheap;
+pub mod netlify;
+pub mod oauth;
+pub mod ovh;
+pub mod pihole;
+pub mod porkbun;
+pub mod powerdns;
+pub mod rfc2136;
+pub mod route53;
+pub mod scaleway;
+pub mod technitium;
+pub mod vercel;
+pub mod vultr;
+pub mod xml;
+pub mod zonefile;
+
+use crate::dns_providers::DnsBackendError;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The temporary file `write_atomic` writes before renaming it to `path`
+pub fn atomic_tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".dns-agent.tmp");
+    path.with_file_name(tmp_name)
+}
+
+/// Write `contents` to a temporary file next to `path` and rename it into place, keeping
+/// the original file's permissions and owner
+pub fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let tmp_path = atomic_tmp_path(path);
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    if let Ok(metadata) = fs::metadata(path) {
+        // Owner first, since changing it can clear setuid and setgid bits
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            std::os::unix::fs::chown(&tmp_path, Some(metadata.uid()), Some(metadata.gid()))?;
+        }
+        fs::set_permissions(&tmp_path, metadata.permissions())?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// Run a reload command (program and arguments, no shell) after a local file was rewritten
+pub fn run_reload_command(command: &[String]) -> Result<(), DnsBackendError> {
+    if let Some((program, args)) = command.split_first() {
+        let status = Command::new(program)
+            .args(args)
+            .status()
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to run reload command {program}: {e}"),
+            })?;
+        if !status.success() {
+            return Err(DnsBackendError {
+                message: format!("Reload command {command:?} failed with {status}"),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Replace a record on APIs that can't edit records in place: create the new record,
+/// then delete the old one. If the delete fails the new record is deleted again, so the
+/// zone is left as it was instead of holding both values.
+pub fn create_then_delete<C, D>(create: C, delete: D, old_id: &str) -> Result<(), DnsBackendError>
+where
+    C: FnOnce() -> Result<String, DnsBackendError>,
+    D: Fn(&str) -> Result<(), DnsBackendError>,
+{
+    let new_id = create()?;
+    if let Err(e) = delete(old_id) {
+        return match delete(&new_id) {
+            Ok(()) => Err(DnsBackendError {
+                message: format!("Failed to delete old record {old_id}, rolled back: {e}"),
+            }),
+            Err(rollback) => Err(DnsBackendError {
+                message: format!(
+                    "Failed to delete old record {old_id}: {e}, and failed to roll back new record {new_id}: {rollback}"
+                ),
+            }),
+        };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_mode_and_owner() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        let dir = std::env::temp_dir().join(format!("dns-agent-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("zone");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        // Only root can hand the file to someone else, which is what we'd see on a server
+        if fs::metadata(&path).unwrap().uid() == 0 {
+            std::os::unix::fs::chown(&path, Some(65534), Some(65534)).unwrap();
+        }
+        let before = fs::metadata(&path).unwrap();
+        write_atomic(&path, "new").unwrap();
+        let after = fs::metadata(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(after.mode() & 0o777, 0o640);
+        assert_eq!((after.uid(), after.gid()), (before.uid(), before.gid()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
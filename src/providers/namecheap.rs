@@ -0,0 +1,320 @@
+use crate::dns_providers::{DnsBackend, DnsBackendError, DnsRecord};
+use crate::providers::xml;
+use reqwest::blocking::Client;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.namecheap.com/xml.response";
+const DEFAULT_TTL: u32 = 1800;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NamecheapBackendConfig {
+    pub api_user: String,
+    pub api_key: String,
+    /// Account the domain belongs to, defaults to `api_user`
+    pub username: Option<String>,
+    /// Whitelisted IPv4 address requests are made from
+    pub client_ip: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, e.g. `https://api.sandbox.namecheap.com/xml.response`
+    pub endpoint: Option<String>,
+}
+
+/// One entry of the domain's host list, kept verbatim so it can be written back
+#[derive(Debug, Clone, PartialEq)]
+struct Host {
+    name: String,
+    kind: String,
+    address: String,
+    mx_pref: String,
+    ttl: String,
+}
+
+struct HostList {
+    hosts: Vec<Host>,
+    email_type: Option<String>,
+}
+
+pub struct NamecheapBackend {
+    client: Client,
+    endpoint: String,
+    api_user: String,
+    api_key: String,
+    username: String,
+    client_ip: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl NamecheapBackend {
+    pub fn new(config: NamecheapBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+            username: config.username.unwrap_or_else(|| config.api_user.clone()),
+            api_user: config.api_user,
+            api_key: config.api_key,
+            client_ip: config.client_ip,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    /// Namecheap addresses domains as second level name plus (possibly multi-label) TLD
+    fn sld_tld(&self) -> Result<(&str, &str), DnsBackendError> {
+        self.zone.split_once('.').ok_or(DnsBackendError {
+            message: format!("{} is not a registered domain name", self.zone),
+        })
+    }
+
+    fn call(
+        &self,
+        command: &str,
+        params: Vec<(String, String)>,
+    ) -> Result<xml::Element, DnsBackendError> {
+        let (sld, tld) = self.sld_tld()?;
+        let mut form = vec![
+            ("ApiUser".to_string(), self.api_user.clone()),
+            ("ApiKey".to_string(), self.api_key.clone()),
+            ("UserName".to_string(), self.username.clone()),
+            ("ClientIp".to_string(), self.client_ip.clone()),
+            ("Command".to_string(), command.to_string()),
+            ("SLD".to_string(), sld.to_string()),
+            ("TLD".to_string(), tld.to_string()),
+        ];
+        form.extend(params);
+        // POST keeps large host lists out of the URL
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .form(&form)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let root = xml::parse(&text).map_err(|e| DnsBackendError {
+            message: format!("Namecheap API returned {status}: {}", e.message),
+        })?;
+        if root.attr("Status") != Some("OK") {
+            let errors: Vec<String> = root
+                .child("Errors")
+                .map(|x| {
+                    x.children_named("Error")
+                        .map(|e| format!("{} {}", e.attr("Number").unwrap_or(""), e.text.trim()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Err(DnsBackendError {
+                message: format!("Namecheap API returned {status}: {}", errors.join(", ")),
+            });
+        }
+        Ok(root)
+    }
+
+    fn get_hosts(&self) -> Result<HostList, DnsBackendError> {
+        let root = self.call("namecheap.domains.dns.getHosts", Vec::new())?;
+        let result = root
+            .find("DomainDNSGetHostsResult")
+            .ok_or(DnsBackendError {
+                message: "Namecheap getHosts response has no DomainDNSGetHostsResult".to_string(),
+            })?;
+        let attr = |host: &xml::Element, name: &str| host.attr(name).unwrap_or("").to_string();
+        let hosts = result
+            .children
+            .iter()
+            .filter(|x| x.name.eq_ignore_ascii_case("host"))
+            .map(|x| Host {
+                name: attr(x, "Name"),
+                kind: attr(x, "Type"),
+                address: attr(x, "Address"),
+                mx_pref: attr(x, "MXPref"),
+                ttl: attr(x, "TTL"),
+            })
+            .collect::<Vec<Host>>();
+        debug!(
+            "Fetched hosts from namecheap for domain {}: {:?}",
+            &self.zone, hosts
+        );
+        Ok(HostList {
+            hosts,
+            email_type: result.attr("EmailType").map(|x| x.to_string()),
+        })
+    }
+
+    /// Replace the domain's entire host list. Every host must be included, anything
+    /// missing is deleted by Namecheap.
+    fn set_hosts(&self, list: &HostList) -> Result<(), DnsBackendError> {
+        let mut params = Vec::new();
+        for (i, host) in list.hosts.iter().enumerate() {
+            let n = i + 1;
+            params.push((format!("HostName{n}"), host.name.clone()));
+            params.push((format!("RecordType{n}"), host.kind.clone()));
+            params.push((format!("Address{n}"), host.address.clone()));
+            params.push((format!("TTL{n}"), host.ttl.clone()));
+            if !host.mx_pref.is_empty() {
+                params.push((format!("MXPref{n}"), host.mx_pref.clone()));
+            }
+        }
+        if let Some(email_type) = &list.email_type {
+            params.push(("EmailType".to_string(), email_type.clone()));
+        }
+        let root = self.call("namecheap.domains.dns.setHosts", params)?;
+        match root
+            .find("DomainDNSSetHostsResult")
+            .and_then(|x| x.attr("IsSuccess"))
+        {
+            Some("true") => Ok(()),
+            _ => Err(DnsBackendError {
+                message: "Namecheap setHosts did not report success".to_string(),
+            }),
+        }
+    }
+}
+
+impl DnsBackend for NamecheapBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            .get_hosts()?
+            .hosts
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: x.kind.as_str().into(),
+                name: x.name,
+                data: x.address,
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let mut list = self.get_hosts()?;
+        list.hosts.push(Host {
+            name: record.name.clone(),
+            kind: record.kind.clone().into(),
+            address: record.data.clone(),
+            mx_pref: String::new(),
+            ttl: self.ttl.to_string(),
+        });
+        self.set_hosts(&list).map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let mut list = self.get_hosts()?;
+        let kind: String = record.kind.clone().into();
+        let existing = list
+            .hosts
+            .iter_mut()
+            .find(|x| x.name == record.name && x.kind == kind && x.address == record.data)
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        existing.address = new_data.to_string();
+        existing.ttl = self.ttl.to_string();
+        self.set_hosts(&list).map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns_providers::DnsRecordType;
+    use crate::test_http::{self, Response};
+
+    const GET_HOSTS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ApiResponse Status="OK" xmlns="http://api.namecheap.com/xml.response">
+  <Errors />
+  <CommandResponse Type="namecheap.domains.dns.getHosts">
+    <DomainDNSGetHostsResult Domain="example.co.uk" EmailType="MX" IsUsingOurDNS="true">
+      <host HostId="1" Name="@" Type="A" Address="192.0.2.1" MXPref="10" TTL="1800" />
+      <host HostId="2" Name="@" Type="MX" Address="mail.example.net." MXPref="5" TTL="3600" />
+      <host HostId="3" Name="www" Type="CNAME" Address="example.co.uk." MXPref="10" TTL="1800" />
+    </DomainDNSGetHostsResult>
+  </CommandResponse>
+</ApiResponse>"#;
+
+    fn backend(endpoint: &str) -> NamecheapBackend {
+        let config = NamecheapBackendConfig {
+            api_user: "user".to_string(),
+            api_key: "key".to_string(),
+            username: None,
+            client_ip: "192.0.2.100".to_string(),
+            ttl: Some(300),
+            endpoint: Some(endpoint.to_string()),
+        };
+        NamecheapBackend::new(config, "example.co.uk".to_string())
+    }
+
+    fn form(body: &str) -> Vec<(String, String)> {
+        reqwest::Url::parse(&format!("http://x/?{body}"))
+            .unwrap()
+            .query_pairs()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn field<'a>(form: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        form.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_update_record_keeps_other_hosts() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.body.contains("getHosts") {
+                Response::new(200, GET_HOSTS)
+            } else {
+                Response::new(
+                    200,
+                    r#"<ApiResponse Status="OK"><Errors /><CommandResponse Type="namecheap.domains.dns.setHosts"><DomainDNSSetHostsResult Domain="example.co.uk" IsSuccess="true" /></CommandResponse></ApiResponse>"#,
+                )
+            }
+        });
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "@".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend(&url).update_record(&record, "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+        let set_hosts = form(&requests[1].body);
+        assert_eq!(field(&set_hosts, "SLD"), Some("example"));
+        assert_eq!(field(&set_hosts, "TLD"), Some("co.uk"));
+        assert_eq!(field(&set_hosts, "UserName"), Some("user"));
+        assert_eq!(field(&set_hosts, "EmailType"), Some("MX"));
+        assert_eq!(field(&set_hosts, "Address1"), Some("192.0.2.2"));
+        assert_eq!(field(&set_hosts, "TTL1"), Some("300"));
+        assert_eq!(field(&set_hosts, "RecordType2"), Some("MX"));
+        assert_eq!(field(&set_hosts, "Address2"), Some("mail.example.net."));
+        assert_eq!(field(&set_hosts, "MXPref2"), Some("5"));
+        assert_eq!(field(&set_hosts, "TTL2"), Some("3600"));
+        assert_eq!(field(&set_hosts, "HostName3"), Some("www"));
+        assert_eq!(field(&set_hosts, "HostName4"), None);
+    }
+
+    #[test]
+    fn test_error_response() {
+        let (url, handle) = test_http::serve(1, |_| {
+            Response::new(
+                200,
+                r#"<ApiResponse Status="ERROR"><Errors><Error Number="1011150">Invalid request IP</Error></Errors></ApiResponse>"#,
+            )
+        });
+        let result = backend(&url).get_zone_records();
+        handle.join().unwrap();
+        assert_eq!(
+            result.unwrap_err().message,
+            "Namecheap API returned 200 OK: 1011150 Invalid request IP"
+        );
+    }
+}
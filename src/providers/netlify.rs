@@ -0,0 +1,247 @@
+use super::create_then_delete;
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.netlify.com/api/v1";
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NetlifyBackendConfig {
+    /// Personal access token
+    pub api_token: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Zone {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct NetlifyRecord {
+    id: String,
+    hostname: String,
+    #[serde(rename = "type")]
+    kind: String,
+    value: String,
+}
+
+#[derive(Serialize, Debug)]
+struct RecordRequest<'a> {
+    #[serde(rename = "type")]
+    kind: String,
+    hostname: String,
+    value: &'a str,
+    ttl: u32,
+}
+
+pub struct NetlifyBackend {
+    client: Client,
+    endpoint: String,
+    api_token: String,
+    zone_id: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl NetlifyBackend {
+    /// Create the backend, looking up the Netlify DNS zone ID for `zone`
+    pub fn new(config: NetlifyBackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let mut backend = Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_token: config.api_token,
+            zone_id: String::new(),
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        };
+        let zones: Vec<Zone> = backend.send(
+            backend
+                .client
+                .get(format!("{}/dns_zones", backend.endpoint)),
+        )?;
+        let zone = zones
+            .into_iter()
+            .find(|x| x.name.eq_ignore_ascii_case(&backend.zone))
+            .ok_or(DnsBackendError {
+                message: format!("Zone {} not found in Netlify account", backend.zone),
+            })?;
+        debug!("Resolved netlify zone {} to ID {}", zone.name, zone.id);
+        backend.zone_id = zone.id;
+        Ok(backend)
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let response =
+            request
+                .bearer_auth(&self.api_token)
+                .send()
+                .map_err(|e| DnsBackendError {
+                    message: format!("Dns backend error: {e:?}"),
+                })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Netlify API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        // Deletes answer 204 No Content
+        let text = response.text().unwrap_or_default();
+        let text = if text.trim().is_empty() {
+            "null"
+        } else {
+            &text
+        };
+        serde_json::from_str(text).map_err(|e| DnsBackendError {
+            message: format!("Could not parse Netlify API response: {e:?}"),
+        })
+    }
+
+    fn records_url(&self) -> String {
+        format!("{}/dns_zones/{}/dns_records", self.endpoint, self.zone_id)
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<NetlifyRecord>, DnsBackendError> {
+        let records: Vec<NetlifyRecord> = self.send(self.client.get(self.records_url()))?;
+        debug!(
+            "Fetched records from netlify for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+
+    fn _create_record_internal(
+        &self,
+        record: &DnsRecord,
+        data: &str,
+    ) -> Result<String, DnsBackendError> {
+        let body = RecordRequest {
+            kind: record.kind.clone().into(),
+            hostname: fqdn(&record.name, &self.zone),
+            value: data,
+            ttl: self.ttl,
+        };
+        let created: NetlifyRecord = self.send(self.client.post(self.records_url()).json(&body))?;
+        Ok(created.id)
+    }
+
+    fn _delete_record_internal(&self, id: &str) -> Result<(), DnsBackendError> {
+        self.send::<serde::de::IgnoredAny>(
+            self.client.delete(format!("{}/{id}", self.records_url())),
+        )?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for NetlifyBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            ._get_records_internal()?
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: x.kind.as_str().into(),
+                name: relative_name(&x.hostname, &self.zone),
+                data: x.value,
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self._create_record_internal(&record, &record.data)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let hostname = fqdn(&record.name, &self.zone);
+        let existing_record = self
+            ._get_records_internal()?
+            .into_iter()
+            .find(|x| {
+                x.hostname.eq_ignore_ascii_case(&hostname)
+                    && DnsRecordType::from(x.kind.as_str()) == record.kind
+                    && x.value == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        // Netlify records can't be edited, only replaced
+        create_then_delete(
+            || self._create_record_internal(record, new_data),
+            |id| self._delete_record_internal(id),
+            &existing_record.id,
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_update_rolls_back_when_delete_fails() {
+        let (url, handle) = test_http::serve(5, |request| {
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/dns_zones") => {
+                    Response::new(200, r#"[{"id":"z1","name":"example.org"}]"#)
+                }
+                ("GET", _) => Response::new(
+                    200,
+                    r#"[{"id":"old","hostname":"staging.example.org","type":"A","value":"192.0.2.1","ttl":3600}]"#,
+                ),
+                ("POST", _) => Response::new(
+                    201,
+                    r#"{"id":"new","hostname":"staging.example.org","type":"A","value":"192.0.2.2","ttl":3600}"#,
+                ),
+                ("DELETE", "/dns_zones/z1/dns_records/old") => {
+                    Response::new(500, r#"{"message":"internal error"}"#)
+                }
+                _ => Response::new(204, ""),
+            }
+        });
+        let config = NetlifyBackendConfig {
+            api_token: "token".to_string(),
+            ttl: None,
+            endpoint: Some(url),
+        };
+        let backend = NetlifyBackend::new(config, "example.org".to_string()).unwrap();
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "staging".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        let error = backend.update_record(&record, "192.0.2.2").unwrap_err();
+        let requests = handle.join().unwrap();
+        assert!(error.message.contains("rolled back"));
+        assert_eq!(
+            requests[2].body,
+            r#"{"type":"A","hostname":"staging.example.org","value":"192.0.2.2","ttl":3600}"#
+        );
+        assert_eq!(requests[4].method, "DELETE");
+        assert_eq!(requests[4].path, "/dns_zones/z1/dns_records/new");
+    }
+}
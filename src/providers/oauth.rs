@@ -0,0 +1,84 @@
+//! OAuth2 access token handling shared by the cloud providers that need one.
+
+use crate::dns_providers::DnsBackendError;
+use reqwest::blocking::Client;
+use serde_derive::Deserialize;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+// Tokens this close to expiring are refreshed before use
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    error_description: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+/// POST a form to a token endpoint and parse the standard OAuth2 token response
+pub fn request_token(
+    client: &Client,
+    token_url: &str,
+    form: &[(&str, &str)],
+) -> Result<AccessToken, DnsBackendError> {
+    let response = client
+        .post(token_url)
+        .form(form)
+        .send()
+        .map_err(|e| DnsBackendError {
+            message: format!("Token request failed: {e:?}"),
+        })?;
+    let status = response.status();
+    let text = response.text().unwrap_or_default();
+    if !status.is_success() {
+        let message = match serde_json::from_str::<TokenError>(&text) {
+            Ok(e) => format!("{} {}", e.error, e.error_description.unwrap_or_default()),
+            Err(_) => text,
+        };
+        return Err(DnsBackendError {
+            message: format!("Token endpoint returned {status}: {}", message.trim()),
+        });
+    }
+    let token: TokenResponse = serde_json::from_str(&text).map_err(|e| DnsBackendError {
+        message: format!("Could not parse token response: {e:?}"),
+    })?;
+    Ok(AccessToken {
+        token: token.access_token,
+        expires_at: Instant::now() + Duration::from_secs(token.expires_in),
+    })
+}
+
+/// Holds the current access token, fetching a new one when it is missing or about to expire
+#[derive(Default)]
+pub struct TokenCache {
+    token: RefCell<Option<AccessToken>>,
+}
+
+impl TokenCache {
+    pub fn get<F>(&self, fetch: F) -> Result<String, DnsBackendError>
+    where
+        F: FnOnce() -> Result<AccessToken, DnsBackendError>,
+    {
+        if let Some(token) = &*self.token.borrow() {
+            if token.expires_at > Instant::now() + REFRESH_MARGIN {
+                return Ok(token.token.clone());
+            }
+        }
+        let token = fetch()?;
+        debug!("Fetched new access token");
+        *self.token.borrow_mut() = Some(token.clone());
+        Ok(token.token)
+    }
+}
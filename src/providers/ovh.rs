@@ -0,0 +1,359 @@
+use crate::dns_providers::{
+    apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use chrono::Utc;
+use reqwest::blocking::Client;
+use reqwest::Method;
+use ring::digest;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OvhBackendConfig {
+    /// `ovh-eu`, `ovh-ca`, `ovh-us` or a full API URL, defaults to `ovh-eu`
+    pub endpoint: Option<String>,
+    pub application_key: String,
+    pub application_secret: String,
+    pub consumer_key: String,
+    pub ttl: Option<u32>,
+}
+
+fn endpoint_url(endpoint: &str) -> String {
+    match endpoint {
+        "ovh-eu" => "https://eu.api.ovh.com/1.0".to_string(),
+        "ovh-ca" => "https://ca.api.ovh.com/1.0".to_string(),
+        "ovh-us" => "https://api.us.ovhcloud.com/1.0".to_string(),
+        url => url.trim_end_matches('/').to_string(),
+    }
+}
+
+/// Compute the `X-Ovh-Signature` header for a request
+pub fn signature(
+    application_secret: &str,
+    consumer_key: &str,
+    method: &str,
+    url: &str,
+    body: &str,
+    timestamp: i64,
+) -> String {
+    let to_sign = format!("{application_secret}+{consumer_key}+{method}+{url}+{body}+{timestamp}");
+    let hash = digest::digest(&digest::SHA1_FOR_LEGACY_USE_ONLY, to_sign.as_bytes());
+    format!("$1${}", hex::encode(hash))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct OvhRecord {
+    id: u64,
+    field_type: String,
+    sub_domain: String,
+    target: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CreateRecordRequest<'a> {
+    field_type: String,
+    sub_domain: &'a str,
+    target: &'a str,
+    ttl: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UpdateRecordRequest<'a> {
+    sub_domain: &'a str,
+    target: &'a str,
+    ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    message: String,
+}
+
+pub struct OvhBackend {
+    client: Client,
+    endpoint: String,
+    application_key: String,
+    application_secret: String,
+    consumer_key: String,
+    /// Seconds to add to the local clock to match OVH's, whose signatures are time-checked
+    time_delta: i64,
+    zone: String,
+    ttl: u32,
+}
+
+impl OvhBackend {
+    /// Create the backend, syncing the request timestamp with the OVH API's clock
+    pub fn new(config: OvhBackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let mut backend = Self {
+            client: Client::new(),
+            endpoint: endpoint_url(config.endpoint.as_deref().unwrap_or("ovh-eu")),
+            application_key: config.application_key,
+            application_secret: config.application_secret,
+            consumer_key: config.consumer_key,
+            time_delta: 0,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        };
+        let server_time: i64 = backend
+            .client
+            .get(format!("{}/auth/time", backend.endpoint))
+            .send()
+            .and_then(|x| x.json())
+            .map_err(|e| DnsBackendError {
+                message: format!("Could not get OVH API time: {e:?}"),
+            })?;
+        backend.time_delta = server_time - Utc::now().timestamp();
+        Ok(backend)
+    }
+
+    /// Send a signed request. `body` is signed exactly as sent, so it's serialized here.
+    fn send<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<T, DnsBackendError> {
+        let url = format!("{}{}", self.endpoint, path);
+        let body = body.unwrap_or_default();
+        let timestamp = Utc::now().timestamp() + self.time_delta;
+        let mut request = self
+            .client
+            .request(method.clone(), &url)
+            .header("X-Ovh-Application", &self.application_key)
+            .header("X-Ovh-Consumer", &self.consumer_key)
+            .header("X-Ovh-Timestamp", timestamp.to_string())
+            .header(
+                "X-Ovh-Signature",
+                signature(
+                    &self.application_secret,
+                    &self.consumer_key,
+                    method.as_str(),
+                    &url,
+                    &body,
+                    timestamp,
+                ),
+            );
+        if !body.is_empty() {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+        let response = request.send().map_err(|e| DnsBackendError {
+            message: format!("Dns backend error: {e:?}"),
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&text)
+                .map(|x| x.message)
+                .unwrap_or(text);
+            return Err(DnsBackendError {
+                message: format!("OVH API returned {status}: {message}"),
+            });
+        }
+        // Some calls answer with an empty body rather than `null`
+        let text = response.text().unwrap_or_default();
+        let text = if text.trim().is_empty() {
+            "null"
+        } else {
+            &text
+        };
+        serde_json::from_str(text).map_err(|e| DnsBackendError {
+            message: format!("Could not parse OVH API response: {e:?}"),
+        })
+    }
+
+    fn to_json<B: serde::Serialize>(body: &B) -> Result<Option<String>, DnsBackendError> {
+        serde_json::to_string(body)
+            .map(Some)
+            .map_err(|e| DnsBackendError {
+                message: format!("Could not serialize OVH request: {e:?}"),
+            })
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<OvhRecord>, DnsBackendError> {
+        // The API only lists record IDs, each record has to be fetched on its own
+        let ids: Vec<u64> = self.send(
+            Method::GET,
+            &format!("/domain/zone/{}/record", self.zone),
+            None,
+        )?;
+        let mut records = Vec::new();
+        for id in ids {
+            let record: OvhRecord = self.send(
+                Method::GET,
+                &format!("/domain/zone/{}/record/{id}", self.zone),
+                None,
+            )?;
+            records.push(record);
+        }
+        debug!(
+            "Fetched records from ovh for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+
+    /// Apply pending record changes to the zone served by OVH's nameservers
+    fn refresh(&self) -> Result<(), DnsBackendError> {
+        self.send::<serde::de::IgnoredAny>(
+            Method::POST,
+            &format!("/domain/zone/{}/refresh", self.zone),
+            None,
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to refresh OVH zone {}: {}", self.zone, e),
+        })?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for OvhBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            ._get_records_internal()?
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: x.field_type.as_str().into(),
+                name: normalize_name(&x.sub_domain, &self.zone),
+                data: x.target,
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let body = CreateRecordRequest {
+            field_type: record.kind.clone().into(),
+            sub_domain: apex_as_empty(&record.name),
+            target: &record.data,
+            ttl: self.ttl,
+        };
+        self.send::<serde::de::IgnoredAny>(
+            Method::POST,
+            &format!("/domain/zone/{}/record", self.zone),
+            Self::to_json(&body)?,
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })?;
+        self.refresh()
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let current_records = self._get_records_internal()?;
+        let existing_record = current_records
+            .into_iter()
+            .find(|x| {
+                x.sub_domain == apex_as_empty(&record.name)
+                    && DnsRecordType::from(x.field_type.as_str()) == record.kind
+                    && x.target == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let body = UpdateRecordRequest {
+            sub_domain: &existing_record.sub_domain,
+            target: new_data,
+            ttl: self.ttl,
+        };
+        self.send::<serde::de::IgnoredAny>(
+            Method::PUT,
+            &format!("/domain/zone/{}/record/{}", self.zone, existing_record.id),
+            Self::to_json(&body)?,
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!(
+                "Failed to update DNS record {existing_record:?} with value {new_data}: {e}"
+            ),
+        })?;
+        self.refresh()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_signature_known_answer() {
+        // Computed independently as "$1$" + hex(SHA1("as+ck+GET+<url>++1700000000"))
+        let url = "https://eu.api.ovh.com/1.0/domain/zone/example.com/record";
+        assert_eq!(
+            signature("as", "ck", "GET", url, "", 1_700_000_000),
+            "$1$c3838f2639ff80d09198f49ed4ffe10dbac765f3"
+        );
+        assert_eq!(
+            signature(
+                "as",
+                "ck",
+                "POST",
+                url,
+                r#"{"fieldType":"A"}"#,
+                1_700_000_000
+            ),
+            "$1$695b65e2e2884b3c6d1f49719061c6efdd3fc39c"
+        );
+    }
+
+    #[test]
+    fn test_signed_update_and_refresh() {
+        let (url, handle) = test_http::serve(5, |request| match request.path.as_str() {
+            "/auth/time" => Response::new(200, &(Utc::now().timestamp() + 100).to_string()),
+            "/domain/zone/example.com/record" => Response::new(200, "[1]"),
+            "/domain/zone/example.com/record/1" if request.method == "GET" => Response::new(
+                200,
+                r#"{"id":1,"zone":"example.com","fieldType":"A","subDomain":"","target":"192.0.2.1","ttl":3600}"#,
+            ),
+            _ => Response::new(200, ""),
+        });
+        let config = OvhBackendConfig {
+            endpoint: Some(url.clone()),
+            application_key: "ak".to_string(),
+            application_secret: "as".to_string(),
+            consumer_key: "ck".to_string(),
+            ttl: None,
+        };
+        let backend = OvhBackend::new(config, "example.com".to_string()).unwrap();
+        assert!((99..=101).contains(&backend.time_delta));
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "@".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend.update_record(&record, "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+
+        let put = &requests[3];
+        assert_eq!(put.method, "PUT");
+        assert_eq!(put.header("x-ovh-application"), Some("ak"));
+        assert_eq!(put.header("x-ovh-consumer"), Some("ck"));
+        let timestamp: i64 = put.header("x-ovh-timestamp").unwrap().parse().unwrap();
+        assert_eq!(
+            put.header("x-ovh-signature").unwrap(),
+            signature(
+                "as",
+                "ck",
+                "PUT",
+                &format!("{url}/domain/zone/example.com/record/1"),
+                &put.body,
+                timestamp
+            )
+        );
+        assert_eq!(
+            put.body,
+            r#"{"subDomain":"","target":"192.0.2.2","ttl":3600}"#
+        );
+        assert_eq!(requests[4].method, "POST");
+        assert_eq!(requests[4].path, "/domain/zone/example.com/refresh");
+    }
+}
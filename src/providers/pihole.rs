@@ -0,0 +1,420 @@
+use crate::dns_providers::{
+    fqdn, in_zone, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use crate::providers::create_then_delete;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::Method;
+use serde_derive::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::net::IpAddr;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PiholeBackendConfig {
+    /// Base URL of the Pi-hole web interface, e.g. `http://pi.hole`
+    pub url: String,
+    /// Web interface or application password
+    pub password: String,
+}
+
+#[derive(Serialize, Debug)]
+struct AuthRequest<'a> {
+    password: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct Session {
+    valid: bool,
+    sid: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AuthResponse {
+    session: Session,
+}
+
+#[derive(Deserialize, Debug)]
+struct DnsConfig {
+    hosts: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Config {
+    dns: DnsConfig,
+}
+
+#[derive(Deserialize, Debug)]
+struct HostsResponse {
+    config: Config,
+}
+
+/// Manages Pi-hole's local DNS records (the `dns.hosts` setting) through the v6 API
+pub struct PiholeBackend {
+    client: Client,
+    url: String,
+    password: String,
+    /// Session ID, logged in on first use and logged out on drop
+    sid: RefCell<Option<String>>,
+    zone: String,
+    /// Hosts lines as last fetched, since a line can hold several names and entries
+    /// can only be deleted by their exact text
+    hosts: RefCell<Vec<String>>,
+}
+
+impl PiholeBackend {
+    pub fn new(config: PiholeBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            url: config.url.trim_end_matches('/').to_string(),
+            password: config.password,
+            sid: RefCell::new(None),
+            zone: zone.trim_end_matches('.').to_string(),
+            hosts: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn login(&self) -> Result<String, DnsBackendError> {
+        if let Some(sid) = &*self.sid.borrow() {
+            return Ok(sid.clone());
+        }
+        let response = self
+            .client
+            .post(format!("{}/api/auth", self.url))
+            .json(&AuthRequest {
+                password: &self.password,
+            })
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let auth: AuthResponse = response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Pi-hole login response: {e:?}"),
+        })?;
+        let sid = match auth.session {
+            Session {
+                valid: true,
+                sid: Some(sid),
+                ..
+            } => sid,
+            // A passwordless Pi-hole hands out no session ID and needs none
+            Session {
+                valid: true,
+                sid: None,
+                ..
+            } => String::new(),
+            Session { message, .. } => {
+                return Err(DnsBackendError {
+                    message: format!(
+                        "Pi-hole login failed: {}",
+                        message.unwrap_or_else(|| "invalid password".to_string())
+                    ),
+                })
+            }
+        };
+        *self.sid.borrow_mut() = Some(sid.clone());
+        Ok(sid)
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<String, DnsBackendError> {
+        let sid = self.login()?;
+        let response = request
+            .header("X-FTL-SID", sid)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!("Pi-hole API returned {status}: {text}"),
+            });
+        }
+        Ok(text)
+    }
+
+    /// Add or remove one `address name...` entry of the hosts list
+    fn change_host(&self, method: Method, entry: &str) -> Result<(), DnsBackendError> {
+        let mut url =
+            reqwest::Url::parse(&format!("{}/api/config/dns/hosts", self.url)).map_err(|e| {
+                DnsBackendError {
+                    message: format!("Invalid Pi-hole URL {}: {e}", self.url),
+                }
+            })?;
+        url.path_segments_mut()
+            .map_err(|_| DnsBackendError {
+                message: format!("Invalid Pi-hole URL {}", self.url),
+            })?
+            .push(entry);
+        self.send(self.client.request(method, url))?;
+        Ok(())
+    }
+
+    /// The stored hosts line holding `record`, or the single-name line we'd have created
+    fn host_line(&self, record: &DnsRecord) -> String {
+        let name = fqdn(&record.name, &self.zone);
+        self.hosts
+            .borrow()
+            .iter()
+            .find(|line| {
+                let mut fields = line.split_whitespace();
+                fields.next() == Some(record.data.as_str())
+                    && fields.any(|x| x.trim_end_matches('.').eq_ignore_ascii_case(&name))
+            })
+            .cloned()
+            .unwrap_or_else(|| format!("{} {name}", record.data))
+    }
+
+    fn check_kind(record: &DnsRecord) -> Result<(), DnsBackendError> {
+        match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => Ok(()),
+            _ => Err(DnsBackendError {
+                message: "Pi-hole local DNS only supports A and AAAA records".to_string(),
+            }),
+        }
+    }
+}
+
+impl Drop for PiholeBackend {
+    fn drop(&mut self) {
+        // Pi-hole only allows a limited number of concurrent sessions, so free ours
+        if let Some(sid) = self.sid.get_mut().take().filter(|x| !x.is_empty()) {
+            let _ = self
+                .client
+                .delete(format!("{}/api/auth", self.url))
+                .header("X-FTL-SID", sid)
+                .send();
+        }
+    }
+}
+
+impl DnsBackend for PiholeBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let text = self.send(
+            self.client
+                .get(format!("{}/api/config/dns/hosts", self.url)),
+        )?;
+        let response: HostsResponse = serde_json::from_str(&text).map_err(|e| DnsBackendError {
+            message: format!("Could not parse Pi-hole API response: {e:?}"),
+        })?;
+        debug!(
+            "Fetched local DNS hosts from pi-hole: {:?}",
+            response.config.dns.hosts
+        );
+        let mut records = Vec::new();
+        for entry in &response.config.dns.hosts {
+            let mut fields = entry.split_whitespace();
+            let address: IpAddr = match fields.next().and_then(|x| x.parse().ok()) {
+                Some(address) => address,
+                None => continue,
+            };
+            for name in fields.filter(|x| in_zone(x, &self.zone)) {
+                records.push(DnsRecord {
+                    kind: if address.is_ipv4() {
+                        DnsRecordType::A
+                    } else {
+                        DnsRecordType::AAAA
+                    },
+                    name: relative_name(name, &self.zone),
+                    data: address.to_string(),
+                });
+            }
+        }
+        *self.hosts.borrow_mut() = response.config.dns.hosts;
+        Ok(records)
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        Self::check_kind(&record)?;
+        let entry = format!("{} {}", record.data, fqdn(&record.name, &self.zone));
+        self.change_host(Method::PUT, &entry)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        Self::check_kind(record)?;
+        // Move just this name to a line of its own, keeping any other names of its old
+        // line at the old address
+        let name = fqdn(&record.name, &self.zone);
+        let old_line = self.host_line(record);
+        let others = old_line
+            .split_whitespace()
+            .skip(1)
+            .filter(|x| !x.trim_end_matches('.').eq_ignore_ascii_case(&name))
+            .collect::<Vec<_>>();
+        let new_line = format!("{new_data} {name}");
+        let rest_line =
+            (!others.is_empty()).then(|| format!("{} {}", record.data, others.join(" ")));
+        // Lines that are already there belong to someone else and must survive a rollback
+        let added = [rest_line.as_ref(), Some(&new_line)]
+            .into_iter()
+            .flatten()
+            .filter(|x| !self.hosts.borrow().contains(x))
+            .cloned()
+            .collect::<Vec<_>>();
+        // Add the new entries first so a failure never leaves the name unresolvable
+        create_then_delete(
+            || {
+                for line in &added {
+                    self.change_host(Method::PUT, line)?;
+                }
+                Ok(new_line.clone())
+            },
+            |line| {
+                if line == old_line {
+                    self.change_host(Method::DELETE, line)
+                } else {
+                    added
+                        .iter()
+                        .try_for_each(|x| self.change_host(Method::DELETE, x))
+                }
+            },
+            &old_line,
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })?;
+        let mut hosts = self.hosts.borrow_mut();
+        hosts.retain(|x| *x != old_line);
+        hosts.extend(added);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    /// A Pi-hole holding `hosts`, answering 404 to deletes of entries it doesn't have
+    fn serve_hosts(
+        count: usize,
+        hosts: &[&str],
+    ) -> (String, std::thread::JoinHandle<Vec<test_http::Request>>) {
+        let mut hosts: Vec<String> = hosts.iter().map(|x| x.to_string()).collect();
+        test_http::serve(count, move |request| {
+            let entry = request
+                .path
+                .strip_prefix("/api/config/dns/hosts/")
+                .map(|x| x.replace("%20", " "));
+            match (request.method.as_str(), entry) {
+                ("POST", _) => Response::new(
+                    200,
+                    r#"{"session":{"valid":true,"totp":false,"sid":"sid123","csrf":"x","validity":1800}}"#,
+                ),
+                ("GET", _) => Response::new(
+                    200,
+                    &format!(r#"{{"config":{{"dns":{{"hosts":{hosts:?}}}}}}}"#),
+                ),
+                ("PUT", Some(entry)) => {
+                    hosts.push(entry);
+                    Response::new(201, "{}")
+                }
+                ("DELETE", Some(entry)) if hosts.contains(&entry) => {
+                    hosts.retain(|x| *x != entry);
+                    Response::new(204, "")
+                }
+                ("DELETE", Some(_)) => Response::new(404, r#"{"error":{"key":"not_found"}}"#),
+                _ => Response::new(204, ""),
+            }
+        })
+    }
+
+    #[test]
+    fn test_session_and_host_changes() {
+        let (url, handle) = serve_hosts(
+            6,
+            &[
+                "192.168.1.10 nas.home.lan nas",
+                "192.168.1.1 router.other.lan",
+            ],
+        );
+        let config = PiholeBackendConfig {
+            url,
+            password: "hunter2".to_string(),
+        };
+        let backend = PiholeBackend::new(config, "home.lan".to_string());
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "nas");
+        backend.update_record(&records[0], "192.168.1.11").unwrap();
+        drop(backend);
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].body, r#"{"password":"hunter2"}"#);
+        assert_eq!(requests[1].header("x-ftl-sid"), Some("sid123"));
+        assert_eq!(requests[2].method, "PUT");
+        assert_eq!(requests[2].path, "/api/config/dns/hosts/192.168.1.10%20nas");
+        assert_eq!(requests[3].method, "PUT");
+        assert_eq!(
+            requests[3].path,
+            "/api/config/dns/hosts/192.168.1.11%20nas.home.lan"
+        );
+        assert_eq!(requests[4].method, "DELETE");
+        assert_eq!(
+            requests[4].path,
+            "/api/config/dns/hosts/192.168.1.10%20nas.home.lan%20nas"
+        );
+        assert_eq!(requests[5].method, "DELETE");
+        assert_eq!(requests[5].path, "/api/auth");
+    }
+
+    #[test]
+    fn test_update_rolls_back_on_failed_delete() {
+        // Login, PUT, failed DELETE, rollback DELETE and logout
+        let (url, handle) = serve_hosts(5, &["192.168.1.10 nas.home.lan nas"]);
+        let config = PiholeBackendConfig {
+            url,
+            password: "hunter2".to_string(),
+        };
+        let backend = PiholeBackend::new(config, "home.lan".to_string());
+        // Not fetched first, so the single-name line is guessed and doesn't match
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "nas".to_string(),
+            data: "192.168.1.10".to_string(),
+        };
+        let error = backend.update_record(&record, "192.168.1.11").unwrap_err();
+        assert!(error.message.contains("rolled back"), "{}", error.message);
+        drop(backend);
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[2].method, "DELETE");
+        assert_eq!(requests[3].method, "DELETE");
+        assert_eq!(
+            requests[3].path,
+            "/api/config/dns/hosts/192.168.1.11%20nas.home.lan"
+        );
+    }
+
+    #[test]
+    fn test_rollback_keeps_existing_lines() {
+        // Login, failed DELETE and logout; the new line is already there, so no PUT
+        let (url, handle) = serve_hosts(3, &["192.168.1.11 nas.home.lan"]);
+        let config = PiholeBackendConfig {
+            url,
+            password: "hunter2".to_string(),
+        };
+        let backend = PiholeBackend::new(config, "home.lan".to_string());
+        *backend.hosts.borrow_mut() = vec!["192.168.1.11 nas.home.lan".to_string()];
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "nas".to_string(),
+            data: "192.168.1.10".to_string(),
+        };
+        let error = backend.update_record(&record, "192.168.1.11").unwrap_err();
+        assert!(error.message.contains("rolled back"), "{}", error.message);
+        drop(backend);
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[1].method, "DELETE");
+        assert_eq!(
+            requests[1].path,
+            "/api/config/dns/hosts/192.168.1.10%20nas.home.lan"
+        );
+        assert_eq!(requests[2].path, "/api/auth");
+    }
+}
@@ -0,0 +1,281 @@
+use crate::dns_providers::{apex_as_empty, relative_name, DnsBackend, DnsBackendError, DnsRecord};
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.porkbun.com/api/json/v3";
+// Porkbun rejects TTLs below 600 seconds
+const DEFAULT_TTL: u32 = 600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PorkbunBackendConfig {
+    pub api_key: String,
+    pub secret_api_key: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Credentials<'a> {
+    apikey: &'a str,
+    secretapikey: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct CreateRequest<'a> {
+    #[serde(flatten)]
+    credentials: Credentials<'a>,
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    content: &'a str,
+    ttl: String,
+}
+
+#[derive(Serialize, Debug)]
+struct EditRequest<'a> {
+    #[serde(flatten)]
+    credentials: Credentials<'a>,
+    content: &'a str,
+    ttl: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Status {
+    status: String,
+    message: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordsResponse {
+    records: Vec<PorkbunRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PorkbunRecord {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    content: String,
+}
+
+pub struct PorkbunBackend {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    secret_api_key: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl PorkbunBackend {
+    pub fn new(config: PorkbunBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: config.api_key,
+            secret_api_key: config.secret_api_key,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    fn credentials(&self) -> Credentials<'_> {
+        Credentials {
+            apikey: &self.api_key,
+            secretapikey: &self.secret_api_key,
+        }
+    }
+
+    /// Every Porkbun call is a POST carrying the API keys in its JSON body
+    fn post<B: serde::Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, DnsBackendError> {
+        let url = format!("{}/{}", self.endpoint, path.trim_end_matches('/'));
+        let response = self
+            .client
+            .post(url)
+            .json(body)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let http_status = response.status();
+        let text = response.text().unwrap_or_default();
+        match serde_json::from_str::<Status>(&text) {
+            Ok(status) if status.status == "SUCCESS" => {}
+            Ok(status) => {
+                return Err(DnsBackendError {
+                    message: format!(
+                        "Porkbun API returned {http_status}: {}",
+                        status.message.unwrap_or(status.status)
+                    ),
+                })
+            }
+            Err(_) => {
+                return Err(DnsBackendError {
+                    message: format!("Porkbun API returned {http_status}: {text}"),
+                })
+            }
+        }
+        serde_json::from_str(&text).map_err(|e| DnsBackendError {
+            message: format!("Could not parse Porkbun API response: {e:?}"),
+        })
+    }
+
+    fn to_records(&self, records: Vec<PorkbunRecord>) -> Vec<DnsRecord> {
+        records
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: x.kind.as_str().into(),
+                name: relative_name(&x.name, &self.zone),
+                data: x.content,
+            })
+            .collect()
+    }
+}
+
+impl DnsBackend for PorkbunBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let response: RecordsResponse =
+            self.post(&format!("dns/retrieve/{}", self.zone), &self.credentials())?;
+        debug!(
+            "Fetched records from porkbun for domain {}: {:?}",
+            &self.zone, response.records
+        );
+        Ok(self.to_records(response.records))
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let kind: String = record.kind.clone().into();
+        let request = CreateRequest {
+            credentials: self.credentials(),
+            name: apex_as_empty(&record.name),
+            kind: &kind,
+            content: &record.data,
+            ttl: self.ttl.to_string(),
+        };
+        self.post::<_, Status>(&format!("dns/create/{}", self.zone), &request)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let kind: String = record.kind.clone().into();
+        let path = format!("{}/{}/{}", self.zone, kind, apex_as_empty(&record.name));
+        // editByNameType silently succeeds when nothing matches, so check first
+        let existing: RecordsResponse = self.post(
+            &format!("dns/retrieveByNameType/{path}"),
+            &self.credentials(),
+        )?;
+        if existing.records.is_empty() {
+            return Err(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            });
+        }
+        let request = EditRequest {
+            credentials: self.credentials(),
+            content: new_data,
+            ttl: self.ttl.to_string(),
+        };
+        self.post::<_, Status>(&format!("dns/editByNameType/{path}"), &request)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {record:?} with value {new_data}: {e}"
+                ),
+            })?;
+        Ok(())
+    }
+
+    fn updates_whole_rrset(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns_providers::DnsRecordType;
+    use crate::test_http::{self, Response};
+
+    fn backend(endpoint: &str) -> PorkbunBackend {
+        let config = PorkbunBackendConfig {
+            api_key: "pk1_key".to_string(),
+            secret_api_key: "sk1_secret".to_string(),
+            ttl: None,
+            endpoint: Some(endpoint.to_string()),
+        };
+        PorkbunBackend::new(config, "example.com".to_string())
+    }
+
+    #[test]
+    fn test_update_record_edits_by_name_and_type() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.path.contains("retrieveByNameType") {
+                Response::new(
+                    200,
+                    r#"{"status":"SUCCESS","records":[{"id":"1","name":"example.com","type":"A","content":"192.0.2.1","ttl":"600","prio":null,"notes":""}]}"#,
+                )
+            } else {
+                Response::new(200, r#"{"status":"SUCCESS"}"#)
+            }
+        });
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "@".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend(&url).update_record(&record, "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].path, "/dns/retrieveByNameType/example.com/A");
+        assert_eq!(requests[1].path, "/dns/editByNameType/example.com/A");
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(body["apikey"], "pk1_key");
+        assert_eq!(body["secretapikey"], "sk1_secret");
+        assert_eq!(body["content"], "192.0.2.2");
+        assert_eq!(body["ttl"], "600");
+    }
+
+    #[test]
+    fn test_get_zone_records_and_errors() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.body.contains("sk1_secret") && request.path == "/dns/retrieve/example.com" {
+                Response::new(
+                    200,
+                    r#"{"status":"SUCCESS","records":[{"id":"1","name":"www.example.com","type":"AAAA","content":"2001:db8::1","ttl":"600"}]}"#,
+                )
+            } else {
+                Response::new(400, r#"{"status":"ERROR","message":"Invalid API key."}"#)
+            }
+        });
+        let records = backend(&url).get_zone_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "www");
+        assert_eq!(records[0].kind, DnsRecordType::AAAA);
+        let error = backend(&url)
+            .create_record(DnsRecord {
+                kind: DnsRecordType::A,
+                name: "www".to_string(),
+                data: "192.0.2.1".to_string(),
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Failed to create DNS record www: Porkbun API returned 400 Bad Request: Invalid API key."
+        );
+        handle.join().unwrap();
+    }
+}
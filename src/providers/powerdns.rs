@@ -0,0 +1,309 @@
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde_derive::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+const DEFAULT_SERVER_ID: &str = "localhost";
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PowerDnsBackendConfig {
+    /// Base URL of the PowerDNS webserver, e.g. `http://127.0.0.1:8081`
+    pub api_url: String,
+    pub api_key: String,
+    /// Defaults to `localhost`, the only server ID the authoritative server uses
+    pub server_id: Option<String>,
+    pub ttl: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PowerDnsRecord {
+    content: String,
+    disabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RRSet {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changetype: Option<String>,
+    records: Vec<PowerDnsRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Zone {
+    rrsets: Vec<RRSet>,
+}
+
+#[derive(Serialize, Debug)]
+struct ZonePatch {
+    rrsets: Vec<RRSet>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: String,
+}
+
+pub struct PowerDnsBackend {
+    client: Client,
+    zone_url: String,
+    api_key: String,
+    zone: String,
+    ttl: u32,
+    /// Disabled records seen by `get_zone_records`, which a REPLACE would otherwise drop
+    disabled: RefCell<Vec<RRSet>>,
+}
+
+impl PowerDnsBackend {
+    pub fn new(config: PowerDnsBackendConfig, zone: String) -> Self {
+        let zone = zone.trim_end_matches('.').to_string();
+        let zone_url = format!(
+            "{}/api/v1/servers/{}/zones/{}.",
+            config.api_url.trim_end_matches('/'),
+            config
+                .server_id
+                .unwrap_or_else(|| DEFAULT_SERVER_ID.to_string()),
+            zone
+        );
+        Self {
+            client: Client::new(),
+            zone_url,
+            api_key: config.api_key,
+            zone,
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+            disabled: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<Response, DnsBackendError> {
+        let response = request
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().unwrap_or_default();
+            let message = match serde_json::from_str::<ErrorResponse>(&text) {
+                Ok(error) => error.error,
+                Err(_) => text,
+            };
+            return Err(DnsBackendError {
+                message: format!("PowerDNS API returned {status}: {message}"),
+            });
+        }
+        Ok(response)
+    }
+
+    fn _get_rrsets_internal(&self) -> Result<Vec<RRSet>, DnsBackendError> {
+        let response = self.send(self.client.get(&self.zone_url))?;
+        let zone: Zone = response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse PowerDNS API response: {e:?}"),
+        })?;
+        debug!(
+            "Fetched rrsets from powerdns for domain {}: {:?}",
+            &self.zone, zone.rrsets
+        );
+        Ok(zone.rrsets)
+    }
+
+    /// Replace the whole RRset for `record`'s name and type with a single enabled value,
+    /// keeping any disabled records of the RRset
+    fn replace_rrset(&self, record: &DnsRecord, data: &str) -> Result<(), DnsBackendError> {
+        let content = match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => data.to_string(),
+            DnsRecordType::TXT => format!("\"{}\"", data.replace('"', "\\\"")),
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Record type not supported".to_string(),
+                })
+            }
+        };
+        let name = format!("{}.", fqdn(&record.name, &self.zone));
+        let kind: String = record.kind.clone().into();
+        let mut records: Vec<PowerDnsRecord> = self
+            .disabled
+            .borrow()
+            .iter()
+            .filter(|x| x.name == name && x.kind == kind)
+            .flat_map(|x| x.records.iter())
+            // PowerDNS rejects RRsets with duplicate contents
+            .filter(|x| x.content != content)
+            .cloned()
+            .collect();
+        records.insert(
+            0,
+            PowerDnsRecord {
+                content,
+                disabled: false,
+            },
+        );
+        let patch = ZonePatch {
+            rrsets: vec![RRSet {
+                name,
+                kind,
+                ttl: Some(self.ttl),
+                changetype: Some("REPLACE".to_string()),
+                records,
+            }],
+        };
+        self.send(self.client.patch(&self.zone_url).json(&patch))?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for PowerDnsBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let rrsets = self._get_rrsets_internal()?;
+        let mut records = Vec::new();
+        let mut disabled = Vec::new();
+        for rrset in rrsets {
+            if rrset.records.iter().any(|x| x.disabled) {
+                disabled.push(RRSet {
+                    records: rrset
+                        .records
+                        .iter()
+                        .filter(|x| x.disabled)
+                        .cloned()
+                        .collect(),
+                    ..rrset.clone()
+                });
+            }
+            let kind: DnsRecordType = rrset.kind.as_str().into();
+            let name = relative_name(&rrset.name, &self.zone);
+            for record in rrset.records.into_iter().filter(|x| !x.disabled) {
+                let data = match kind {
+                    DnsRecordType::TXT => record.content.trim_matches('"').replace("\\\"", "\""),
+                    _ => record.content,
+                };
+                records.push(DnsRecord {
+                    kind: kind.clone(),
+                    name: name.clone(),
+                    data,
+                });
+            }
+        }
+        self.disabled.replace(disabled);
+        Ok(records)
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self.replace_rrset(&record, &record.data)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        self.replace_rrset(record, new_data)
+            .map_err(|e| DnsBackendError {
+                message: format!(
+                    "Failed to update DNS record {record:?} with value {new_data}: {e}"
+                ),
+            })
+    }
+
+    fn updates_whole_rrset(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_replace_keeps_disabled_records() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.method == "GET" {
+                Response::new(
+                    200,
+                    r#"{"rrsets":[{"name":"www.example.com.","type":"A","ttl":3600,"records":[{"content":"192.0.2.1","disabled":false},{"content":"192.0.2.7","disabled":true}]}]}"#,
+                )
+            } else {
+                Response::new(204, "")
+            }
+        });
+        let config = PowerDnsBackendConfig {
+            api_url: format!("{url}/"),
+            api_key: "secret".to_string(),
+            server_id: None,
+            ttl: Some(60),
+        };
+        let backend = PowerDnsBackend::new(config, "example.com.".to_string());
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "www");
+        backend.update_record(&records[0], "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].header("X-API-Key"), Some("secret"));
+        assert_eq!(requests[1].header("X-API-Key"), Some("secret"));
+        assert_eq!(requests[1].method, "PATCH");
+        assert_eq!(
+            requests[1].path,
+            "/api/v1/servers/localhost/zones/example.com."
+        );
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"rrsets": [{
+                "name": "www.example.com.",
+                "type": "A",
+                "ttl": 60,
+                "changetype": "REPLACE",
+                "records": [
+                    {"content": "192.0.2.2", "disabled": false},
+                    {"content": "192.0.2.7", "disabled": true}
+                ]
+            }]})
+        );
+    }
+
+    #[test]
+    fn test_error_message() {
+        let (url, handle) = test_http::serve(1, |_| {
+            Response::new(
+                422,
+                r#"{"error":"RRset www.example.com. IN TXT: bad content"}"#,
+            )
+        });
+        let config = PowerDnsBackendConfig {
+            api_url: url,
+            api_key: "secret".to_string(),
+            server_id: Some("ns1".to_string()),
+            ttl: None,
+        };
+        let error = PowerDnsBackend::new(config, "example.com".to_string())
+            .create_record(DnsRecord {
+                kind: DnsRecordType::TXT,
+                name: "www".to_string(),
+                data: "say \"hi\"".to_string(),
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Failed to create DNS record www: PowerDNS API returned 422 Unprocessable Entity: RRset www.example.com. IN TXT: bad content"
+        );
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].path, "/api/v1/servers/ns1/zones/example.com.");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(
+            body["rrsets"][0]["records"],
+            serde_json::json!([{"content": "\"say \\\"hi\\\"\"", "disabled": false}])
+        );
+        assert_eq!(body["rrsets"][0]["ttl"], DEFAULT_TTL);
+    }
+}
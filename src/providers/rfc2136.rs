@@ -0,0 +1,946 @@
+use crate::dns_message::{
+    self, split_last_additional, Message, ResourceRecord, CLASS_ANY, CLASS_IN, CLASS_NONE,
+    RCODE_NOERROR, TYPE_A, TYPE_AAAA, TYPE_AXFR, TYPE_NS, TYPE_SOA, TYPE_SRV, TYPE_TSIG, TYPE_TXT,
+};
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_derive::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_PORT: u16 = 53;
+const DEFAULT_TTL: u32 = 3600;
+const TSIG_FUDGE: u16 = 300;
+/// Most unsigned messages allowed between signed ones in a multi-message response
+const MAX_UNSIGNED_MESSAGES: usize = 99;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TsigAlgorithm {
+    HmacSha256,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    fn wire_name(&self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacSha256 => "hmac-sha256",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512",
+        }
+    }
+
+    fn hmac_algorithm(&self) -> hmac::Algorithm {
+        match self {
+            TsigAlgorithm::HmacSha256 => hmac::HMAC_SHA256,
+            TsigAlgorithm::HmacSha512 => hmac::HMAC_SHA512,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Rfc2136BackendConfig {
+    /// Primary nameserver accepting updates, as a hostname or IP address
+    pub server: String,
+    pub port: Option<u16>,
+    pub key_name: String,
+    pub algorithm: TsigAlgorithm,
+    /// Base64 encoded TSIG secret, as found in a BIND/Knot key file
+    pub secret: String,
+    /// Send updates over TCP instead of UDP (zone transfers always use TCP)
+    pub tcp: Option<bool>,
+    pub ttl: Option<u32>,
+}
+
+/// A TSIG key (RFC 8945) used to sign requests and verify responses
+pub struct TsigKey {
+    name: String,
+    algorithm: TsigAlgorithm,
+    key: hmac::Key,
+}
+
+struct TsigData {
+    algorithm: String,
+    time_signed: u64,
+    fudge: u16,
+    mac: Vec<u8>,
+    error: u16,
+    other: Vec<u8>,
+}
+
+impl TsigData {
+    fn decode(rdata: &[u8]) -> Result<Self, DnsBackendError> {
+        let malformed = || DnsBackendError {
+            message: "Malformed TSIG record in response".to_string(),
+        };
+        let (algorithm, mut pos) = dns_message::read_name(rdata, 0).map_err(|_| malformed())?;
+        let fixed = rdata.get(pos..pos + 10).ok_or_else(malformed)?;
+        let time_signed = u64::from_be_bytes([
+            0, 0, fixed[0], fixed[1], fixed[2], fixed[3], fixed[4], fixed[5],
+        ]);
+        let fudge = u16::from_be_bytes([fixed[6], fixed[7]]);
+        let mac_len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        pos += 10;
+        let mac = rdata
+            .get(pos..pos + mac_len)
+            .ok_or_else(malformed)?
+            .to_vec();
+        pos += mac_len;
+        // Original ID, error and other length
+        let trailer = rdata.get(pos..pos + 6).ok_or_else(malformed)?;
+        let error = u16::from_be_bytes([trailer[2], trailer[3]]);
+        let other_len = u16::from_be_bytes([trailer[4], trailer[5]]) as usize;
+        pos += 6;
+        let other = rdata
+            .get(pos..pos + other_len)
+            .ok_or_else(malformed)?
+            .to_vec();
+        Ok(TsigData {
+            algorithm,
+            time_signed,
+            fudge,
+            mac,
+            error,
+            other,
+        })
+    }
+}
+
+impl TsigKey {
+    pub fn new(
+        name: &str,
+        algorithm: TsigAlgorithm,
+        secret: &str,
+    ) -> Result<Self, DnsBackendError> {
+        let secret = STANDARD
+            .decode(secret.trim())
+            .map_err(|e| DnsBackendError {
+                message: format!("TSIG secret for {name} is not valid base64: {e}"),
+            })?;
+        // Checked here so signing can't fail on the key name later
+        dns_message::encode_name(name, &mut Vec::new())?;
+        Ok(TsigKey {
+            name: name.trim_end_matches('.').to_lowercase(),
+            algorithm,
+            key: hmac::Key::new(algorithm.hmac_algorithm(), &secret),
+        })
+    }
+
+    fn digest_input(
+        &self,
+        request_mac: Option<&[u8]>,
+        message: &[u8],
+        time_signed: u64,
+        fudge: u16,
+        error: u16,
+        other: &[u8],
+    ) -> Result<Vec<u8>, DnsBackendError> {
+        let mut input = Vec::with_capacity(message.len() + 128);
+        if let Some(request_mac) = request_mac {
+            input.extend_from_slice(&(request_mac.len() as u16).to_be_bytes());
+            input.extend_from_slice(request_mac);
+        }
+        input.extend_from_slice(message);
+        dns_message::encode_name(&self.name, &mut input)?;
+        input.extend_from_slice(&CLASS_ANY.to_be_bytes());
+        input.extend_from_slice(&0u32.to_be_bytes());
+        dns_message::encode_name(self.algorithm.wire_name(), &mut input)?;
+        input.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        input.extend_from_slice(&fudge.to_be_bytes());
+        input.extend_from_slice(&error.to_be_bytes());
+        input.extend_from_slice(&(other.len() as u16).to_be_bytes());
+        input.extend_from_slice(other);
+        Ok(input)
+    }
+
+    /// Append a TSIG record to `message`, returning the MAC so the response can be verified.
+    /// `request_mac` is only set when signing a response. Fails for messages with names
+    /// that can't be encoded.
+    pub fn sign(
+        &self,
+        message: &mut Message,
+        request_mac: Option<&[u8]>,
+        time_signed: u64,
+    ) -> Result<Vec<u8>, DnsBackendError> {
+        let input = self.digest_input(
+            request_mac,
+            &message.encode()?,
+            time_signed,
+            TSIG_FUDGE,
+            0,
+            &[],
+        )?;
+        let mac = hmac::sign(&self.key, &input).as_ref().to_vec();
+        let record = self.tsig_record(message.id, time_signed, &mac)?;
+        message.additional.push(record);
+        Ok(mac)
+    }
+
+    fn tsig_record(
+        &self,
+        id: u16,
+        time_signed: u64,
+        mac: &[u8],
+    ) -> Result<ResourceRecord, DnsBackendError> {
+        let mut rdata = Vec::new();
+        dns_message::encode_name(self.algorithm.wire_name(), &mut rdata)?;
+        rdata.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        rdata.extend_from_slice(&TSIG_FUDGE.to_be_bytes());
+        rdata.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(mac);
+        rdata.extend_from_slice(&id.to_be_bytes());
+        rdata.extend_from_slice(&0u16.to_be_bytes());
+        rdata.extend_from_slice(&0u16.to_be_bytes());
+        Ok(ResourceRecord {
+            name: self.name.clone(),
+            rtype: TYPE_TSIG,
+            class: CLASS_ANY,
+            ttl: 0,
+            rdata,
+        })
+    }
+
+    /// Digest input for a later message of a multi-message response (RFC 8945 section
+    /// 5.3.1): the previous MAC, the unsigned messages since, this message and only the
+    /// TSIG timers
+    fn continuation_input(
+        prior_mac: &[u8],
+        unsigned: &[u8],
+        message: &[u8],
+        time_signed: u64,
+        fudge: u16,
+    ) -> Vec<u8> {
+        let mut input = Vec::with_capacity(prior_mac.len() + unsigned.len() + message.len() + 10);
+        input.extend_from_slice(&(prior_mac.len() as u16).to_be_bytes());
+        input.extend_from_slice(prior_mac);
+        input.extend_from_slice(unsigned);
+        input.extend_from_slice(message);
+        input.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        input.extend_from_slice(&fudge.to_be_bytes());
+        input
+    }
+
+    /// Verify the TSIG record at the end of a raw message, returning its MAC
+    pub fn verify(
+        &self,
+        data: &[u8],
+        request_mac: Option<&[u8]>,
+        now: u64,
+    ) -> Result<Vec<u8>, DnsBackendError> {
+        let (unsigned, tsig) = self.split_tsig(data)?;
+        let input = self.digest_input(
+            request_mac,
+            &unsigned,
+            tsig.time_signed,
+            tsig.fudge,
+            tsig.error,
+            &tsig.other,
+        )?;
+        Self::check_mac(&self.key, &input, tsig, now)
+    }
+
+    /// Verify the TSIG record of a later message in a multi-message response such as a
+    /// zone transfer. `unsigned` holds the raw unsigned messages since `prior_mac`.
+    pub fn verify_continuation(
+        &self,
+        data: &[u8],
+        prior_mac: &[u8],
+        unsigned: &[u8],
+        now: u64,
+    ) -> Result<Vec<u8>, DnsBackendError> {
+        let (message, tsig) = self.split_tsig(data)?;
+        let input =
+            Self::continuation_input(prior_mac, unsigned, &message, tsig.time_signed, tsig.fudge);
+        Self::check_mac(&self.key, &input, tsig, now)
+    }
+
+    /// Split the TSIG record off a raw message, checking it was made with this key
+    fn split_tsig(&self, data: &[u8]) -> Result<(Vec<u8>, TsigData), DnsBackendError> {
+        let (unsigned, record) = split_last_additional(data).map_err(|_| DnsBackendError {
+            message: "Response is not TSIG signed".to_string(),
+        })?;
+        if record.rtype != TYPE_TSIG {
+            return Err(DnsBackendError {
+                message: "Response is not TSIG signed".to_string(),
+            });
+        }
+        let tsig = TsigData::decode(&record.rdata)?;
+        if tsig.error != 0 {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Server rejected TSIG key {}: {}",
+                    self.name,
+                    tsig_error_name(tsig.error)
+                ),
+            });
+        }
+        if !tsig
+            .algorithm
+            .eq_ignore_ascii_case(self.algorithm.wire_name())
+            || !record
+                .name
+                .trim_end_matches('.')
+                .eq_ignore_ascii_case(&self.name)
+        {
+            return Err(DnsBackendError {
+                message: format!("Response signed with unexpected key {}", record.name),
+            });
+        }
+        Ok((unsigned, tsig))
+    }
+
+    fn check_mac(
+        key: &hmac::Key,
+        input: &[u8],
+        tsig: TsigData,
+        now: u64,
+    ) -> Result<Vec<u8>, DnsBackendError> {
+        hmac::verify(key, input, &tsig.mac).map_err(|_| DnsBackendError {
+            message: "TSIG signature verification failed".to_string(),
+        })?;
+        if now.abs_diff(tsig.time_signed) > tsig.fudge as u64 {
+            return Err(DnsBackendError {
+                message: "TSIG signature time outside of allowed fudge".to_string(),
+            });
+        }
+        Ok(tsig.mac)
+    }
+}
+
+fn tsig_error_name(error: u16) -> String {
+    match error {
+        16 => "BADSIG".to_string(),
+        17 => "BADKEY".to_string(),
+        18 => "BADTIME".to_string(),
+        22 => "BADTRUNC".to_string(),
+        other => format!("error {other}"),
+    }
+}
+
+fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        6 => "YXDOMAIN".to_string(),
+        7 => "YXRRSET".to_string(),
+        8 => "NXRRSET".to_string(),
+        9 => "NOTAUTH".to_string(),
+        10 => "NOTZONE".to_string(),
+        other => format!("rcode {other}"),
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn record_type_code(kind: &DnsRecordType) -> Option<u16> {
+    match kind {
+        DnsRecordType::A => Some(TYPE_A),
+        DnsRecordType::AAAA => Some(TYPE_AAAA),
+        DnsRecordType::TXT => Some(TYPE_TXT),
+        DnsRecordType::NS => Some(TYPE_NS),
+        DnsRecordType::SRV => Some(TYPE_SRV),
+        DnsRecordType::Other => None,
+    }
+}
+
+fn record_type_from_code(code: u16) -> DnsRecordType {
+    match code {
+        TYPE_A => DnsRecordType::A,
+        TYPE_AAAA => DnsRecordType::AAAA,
+        TYPE_TXT => DnsRecordType::TXT,
+        TYPE_NS => DnsRecordType::NS,
+        TYPE_SRV => DnsRecordType::SRV,
+        _ => DnsRecordType::Other,
+    }
+}
+
+fn encode_rdata(kind: &DnsRecordType, data: &str) -> Result<Vec<u8>, DnsBackendError> {
+    let invalid = || DnsBackendError {
+        message: format!("Invalid {kind:?} record data: {data}"),
+    };
+    match kind {
+        DnsRecordType::A => Ok(data
+            .parse::<Ipv4Addr>()
+            .map_err(|_| invalid())?
+            .octets()
+            .to_vec()),
+        DnsRecordType::AAAA => Ok(data
+            .parse::<Ipv6Addr>()
+            .map_err(|_| invalid())?
+            .octets()
+            .to_vec()),
+        DnsRecordType::TXT => {
+            let mut rdata = Vec::new();
+            for chunk in data.as_bytes().chunks(255) {
+                rdata.push(chunk.len() as u8);
+                rdata.extend_from_slice(chunk);
+            }
+            Ok(rdata)
+        }
+        _ => Err(DnsBackendError {
+            message: "Record type not supported".to_string(),
+        }),
+    }
+}
+
+pub struct Rfc2136Backend {
+    server: SocketAddr,
+    key: TsigKey,
+    zone: String,
+    tcp: bool,
+    ttl: u32,
+}
+
+impl Rfc2136Backend {
+    pub fn new(config: Rfc2136BackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let port = config.port.unwrap_or(DEFAULT_PORT);
+        let server = match config.server.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, port),
+            Err(_) => (config.server.as_str(), port)
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or(DnsBackendError {
+                    message: format!("Could not resolve nameserver {}", config.server),
+                })?,
+        };
+        Ok(Self {
+            server,
+            key: TsigKey::new(&config.key_name, config.algorithm, &config.secret)?,
+            zone: zone.trim_end_matches('.').to_string(),
+            tcp: config.tcp.unwrap_or(false),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        })
+    }
+
+    fn new_id() -> u16 {
+        let mut id = [0u8; 2];
+        SystemRandom::new()
+            .fill(&mut id)
+            .expect("Failed to generate DNS message ID");
+        u16::from_be_bytes(id)
+    }
+
+    fn send_update(&self, mut message: Message) -> Result<(), DnsBackendError> {
+        let request_mac = self.key.sign(&mut message, None, unix_time())?;
+        let mut response = if self.tcp {
+            self.exchange_tcp(&message)?
+        } else {
+            dns_message::exchange_udp(self.server, &message, TIMEOUT).map_err(|e| {
+                DnsBackendError {
+                    message: format!("Dns backend error: {e}"),
+                }
+            })?
+        };
+        let mut decoded = decode_response(&response)?;
+        if decoded.is_truncated() {
+            debug!(
+                "UDP response from {} truncated, retrying over TCP",
+                self.server
+            );
+            response = self.exchange_tcp(&message)?;
+            decoded = decode_response(&response)?;
+        }
+        if decoded.rcode() != RCODE_NOERROR {
+            // Failed responses may be unsigned (e.g. NOTAUTH for an unknown key), so only
+            // surface TSIG problems when the server actually attached a signature
+            if decoded.additional.iter().any(|x| x.rtype == TYPE_TSIG) {
+                self.key
+                    .verify(&response, Some(&request_mac), unix_time())?;
+            }
+            return Err(DnsBackendError {
+                message: format!(
+                    "Update of zone {} refused by {}: {}",
+                    self.zone,
+                    self.server,
+                    rcode_name(decoded.rcode())
+                ),
+            });
+        }
+        self.key
+            .verify(&response, Some(&request_mac), unix_time())?;
+        Ok(())
+    }
+
+    fn exchange_tcp(&self, message: &Message) -> Result<Vec<u8>, DnsBackendError> {
+        let transport_error = |e: dns_message::DnsMessageError| DnsBackendError {
+            message: format!("Dns backend error: {e}"),
+        };
+        let mut stream = dns_message::connect_tcp(self.server, TIMEOUT).map_err(transport_error)?;
+        dns_message::write_tcp_message(&mut stream, &message.encode()?).map_err(transport_error)?;
+        let response = dns_message::read_tcp_message(&mut stream).map_err(transport_error)?;
+        // UDP responses are matched on their ID by exchange_udp, TCP ones have to be checked
+        if response.get(..2) != Some(&message.id.to_be_bytes()[..]) {
+            return Err(DnsBackendError {
+                message: format!("Response from {} has a mismatched ID", self.server),
+            });
+        }
+        Ok(response)
+    }
+
+    fn _transfer_zone(&self) -> Result<Vec<ResourceRecord>, DnsBackendError> {
+        let transport_error = |e: dns_message::DnsMessageError| DnsBackendError {
+            message: format!(
+                "Dns backend error during zone transfer of {}: {e}",
+                self.zone
+            ),
+        };
+        let mut query = Message::new_query(Self::new_id(), &self.zone, TYPE_AXFR, CLASS_IN);
+        // Zone transfers are not recursive
+        query.flags = 0;
+        let request_mac = self.key.sign(&mut query, None, unix_time())?;
+
+        let mut stream = dns_message::connect_tcp(self.server, TIMEOUT).map_err(transport_error)?;
+        dns_message::write_tcp_message(&mut stream, &query.encode()?).map_err(transport_error)?;
+
+        let mut records = Vec::new();
+        let mut soa_count = 0;
+        let mut prior_mac: Option<Vec<u8>> = None;
+        // Raw messages since the last signed one, which its MAC also covers
+        let mut unsigned = Vec::new();
+        let mut unsigned_count = 0;
+        while soa_count < 2 {
+            let raw = dns_message::read_tcp_message(&mut stream).map_err(transport_error)?;
+            let message = decode_response(&raw)?;
+            if message.id != query.id {
+                return Err(DnsBackendError {
+                    message: "Zone transfer response ID mismatch".to_string(),
+                });
+            }
+            if message.rcode() != RCODE_NOERROR {
+                return Err(DnsBackendError {
+                    message: format!(
+                        "Zone transfer of {} refused by {}: {}",
+                        self.zone,
+                        self.server,
+                        rcode_name(message.rcode())
+                    ),
+                });
+            }
+            // The first message must be signed, later ones may leave the signature to a
+            // following message (RFC 8945 section 5.3.1)
+            let signed = message
+                .additional
+                .last()
+                .is_some_and(|x| x.rtype == TYPE_TSIG);
+            match &prior_mac {
+                None => prior_mac = Some(self.key.verify(&raw, Some(&request_mac), unix_time())?),
+                Some(mac) if signed => {
+                    let mac = self
+                        .key
+                        .verify_continuation(&raw, mac, &unsigned, unix_time())?;
+                    prior_mac = Some(mac);
+                    unsigned.clear();
+                    unsigned_count = 0;
+                }
+                Some(_) => {
+                    unsigned_count += 1;
+                    if unsigned_count > MAX_UNSIGNED_MESSAGES {
+                        return Err(DnsBackendError {
+                            message: "Zone transfer has too many unsigned messages".to_string(),
+                        });
+                    }
+                    unsigned.extend_from_slice(&raw);
+                }
+            }
+            if message.answers.is_empty() {
+                return Err(DnsBackendError {
+                    message: "Zone transfer ended unexpectedly".to_string(),
+                });
+            }
+            for record in message.answers {
+                if record.rtype == TYPE_SOA {
+                    soa_count += 1;
+                } else {
+                    records.push(record);
+                }
+            }
+        }
+        if unsigned_count > 0 {
+            return Err(DnsBackendError {
+                message: "Zone transfer ended with an unsigned message".to_string(),
+            });
+        }
+        debug!(
+            "Transferred {} records from {} for zone {}",
+            records.len(),
+            self.server,
+            self.zone
+        );
+        Ok(records)
+    }
+}
+
+fn decode_response(data: &[u8]) -> Result<Message, DnsBackendError> {
+    Message::decode(data).map_err(|e| DnsBackendError {
+        message: format!("Dns backend error: {e}"),
+    })
+}
+
+impl DnsBackend for Rfc2136Backend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let records = self._transfer_zone()?;
+        Ok(records
+            .iter()
+            .map(|x| DnsRecord {
+                kind: record_type_from_code(x.rtype),
+                name: relative_name(&x.name, &self.zone),
+                data: x.data_string().unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let rtype = record_type_code(&record.kind).ok_or(DnsBackendError {
+            message: "Record type not supported".to_string(),
+        })?;
+        let mut message = Message::new_update(Self::new_id(), &self.zone);
+        message.authority.push(ResourceRecord {
+            name: fqdn(&record.name, &self.zone),
+            rtype,
+            class: CLASS_IN,
+            ttl: self.ttl,
+            rdata: encode_rdata(&record.kind, &record.data)?,
+        });
+        self.send_update(message)
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let rtype = record_type_code(&record.kind).ok_or(DnsBackendError {
+            message: "Record type not supported".to_string(),
+        })?;
+        let name = fqdn(&record.name, &self.zone);
+        let mut message = Message::new_update(Self::new_id(), &self.zone);
+        // Delete exactly the old RR, then add the new one, in a single atomic update
+        message.authority.push(ResourceRecord {
+            name: name.clone(),
+            rtype,
+            class: CLASS_NONE,
+            ttl: 0,
+            rdata: encode_rdata(&record.kind, &record.data)?,
+        });
+        message.authority.push(ResourceRecord {
+            name,
+            rtype,
+            class: CLASS_IN,
+            ttl: self.ttl,
+            rdata: encode_rdata(&record.kind, new_data)?,
+        });
+        self.send_update(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, UdpSocket};
+    use std::thread;
+
+    const SECRET: &str = "c2VjcmV0LWtleS1mb3ItdGVzdGluZy1kbnMtYWdlbnQ=";
+
+    fn test_key() -> TsigKey {
+        TsigKey::new("agent-key", TsigAlgorithm::HmacSha256, SECRET).unwrap()
+    }
+
+    fn backend(server: SocketAddr, secret: &str) -> Rfc2136Backend {
+        let config = Rfc2136BackendConfig {
+            server: server.ip().to_string(),
+            port: Some(server.port()),
+            key_name: "agent-key".to_string(),
+            algorithm: TsigAlgorithm::HmacSha256,
+            secret: secret.to_string(),
+            tcp: None,
+            ttl: Some(60),
+        };
+        Rfc2136Backend::new(config, "example.com".to_string()).unwrap()
+    }
+
+    /// Answer a single UPDATE over UDP, returning the decoded request
+    fn update_server() -> (SocketAddr, thread::JoinHandle<Message>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let key = test_key();
+            let mut buf = [0u8; 4096];
+            let (len, peer) = socket.recv_from(&mut buf).unwrap();
+            let request = Message::decode(&buf[..len]).unwrap();
+            let mut response = request.response(RCODE_NOERROR);
+            match key.verify(&buf[..len], None, unix_time()) {
+                Ok(mac) => {
+                    key.sign(&mut response, Some(&mac), unix_time()).unwrap();
+                }
+                // NOTAUTH, unsigned
+                Err(_) => response.flags |= 9,
+            }
+            socket.send_to(&response.encode().unwrap(), peer).unwrap();
+            request
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_tsig_sign_verify_roundtrip() {
+        let key = test_key();
+        let mut message = Message::new_update(7, "example.com");
+        let mac = key.sign(&mut message, None, 1_700_000_000).unwrap();
+        assert_eq!(
+            key.verify(&message.encode().unwrap(), None, 1_700_000_100)
+                .unwrap(),
+            mac
+        );
+        assert!(key
+            .verify(&message.encode().unwrap(), None, 1_700_001_000)
+            .is_err());
+        let other = TsigKey::new("agent-key", TsigAlgorithm::HmacSha256, "b3RoZXI=").unwrap();
+        assert!(other
+            .verify(&message.encode().unwrap(), None, 1_700_000_000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_update_record_replaces_rr() {
+        let (addr, handle) = update_server();
+        let backend = backend(addr, SECRET);
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "host.site1".to_string(),
+            data: "10.0.0.1".to_string(),
+        };
+        backend.update_record(&record, "10.0.0.2").unwrap();
+        let request = handle.join().unwrap();
+        assert_eq!(request.opcode(), dns_message::OPCODE_UPDATE);
+        assert_eq!(request.questions[0].name, "example.com");
+        assert_eq!(request.authority.len(), 2);
+        assert_eq!(request.authority[0].name, "host.site1.example.com");
+        assert_eq!(request.authority[0].class, CLASS_NONE);
+        assert_eq!(request.authority[0].rdata, vec![10, 0, 0, 1]);
+        assert_eq!(request.authority[1].class, CLASS_IN);
+        assert_eq!(request.authority[1].ttl, 60);
+        assert_eq!(request.authority[1].rdata, vec![10, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_update_rejects_long_label() {
+        // Nothing listens here, the update must fail before anything is sent
+        let backend = backend("127.0.0.1:9".parse().unwrap(), SECRET);
+        let name = "a".repeat(64);
+        let error = backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::A,
+                name: name.clone(),
+                data: "10.0.0.1".to_string(),
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            format!("Invalid DNS name {name}.example.com: label longer than 63 bytes")
+        );
+    }
+
+    #[test]
+    fn test_update_with_wrong_key_fails() {
+        let (addr, handle) = update_server();
+        let backend = backend(addr, "d3Jvbmcta2V5");
+        let record = DnsRecord {
+            kind: DnsRecordType::AAAA,
+            name: "host".to_string(),
+            data: "2001:db8::1".to_string(),
+        };
+        let result = backend.create_record(record);
+        handle.join().unwrap();
+        assert_eq!(
+            result,
+            Err(DnsBackendError {
+                message: format!("Update of zone example.com refused by {addr}: NOTAUTH")
+            })
+        );
+    }
+
+    enum Signature {
+        Signed,
+        Unsigned,
+        /// Signed with a different secret
+        Forged,
+    }
+
+    fn soa() -> ResourceRecord {
+        ResourceRecord {
+            name: "example.com".to_string(),
+            rtype: TYPE_SOA,
+            class: CLASS_IN,
+            ttl: 3600,
+            rdata: vec![0; 22],
+        }
+    }
+
+    /// Answer a single AXFR with one message per entry of `envelopes`, chaining the
+    /// signatures like RFC 8945 section 5.3.1 describes
+    fn axfr_server(
+        envelopes: Vec<(Vec<ResourceRecord>, Signature)>,
+    ) -> (SocketAddr, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let key = test_key();
+            let forged = TsigKey::new("agent-key", TsigAlgorithm::HmacSha256, "b3RoZXI=").unwrap();
+            let (mut stream, _) = listener.accept().unwrap();
+            let raw = dns_message::read_tcp_message(&mut stream).unwrap();
+            let request = Message::decode(&raw).unwrap();
+            let mut mac = key.verify(&raw, None, unix_time()).unwrap();
+            let mut unsigned = Vec::new();
+            for (index, (answers, signature)) in envelopes.into_iter().enumerate() {
+                let mut response = request.response(RCODE_NOERROR);
+                response.answers = answers;
+                let key = match signature {
+                    Signature::Unsigned => {
+                        unsigned.extend_from_slice(&response.encode().unwrap());
+                        dns_message::write_tcp_message(&mut stream, &response.encode().unwrap())
+                            .unwrap();
+                        continue;
+                    }
+                    Signature::Signed => &key,
+                    Signature::Forged => &forged,
+                };
+                if index == 0 {
+                    mac = key.sign(&mut response, Some(&mac), unix_time()).unwrap();
+                } else {
+                    let time_signed = unix_time();
+                    let input = TsigKey::continuation_input(
+                        &mac,
+                        &unsigned,
+                        &response.encode().unwrap(),
+                        time_signed,
+                        TSIG_FUDGE,
+                    );
+                    mac = hmac::sign(&key.key, &input).as_ref().to_vec();
+                    let record = key.tsig_record(response.id, time_signed, &mac).unwrap();
+                    response.additional.push(record);
+                    unsigned.clear();
+                }
+                // The client may hang up after a bad signature
+                if dns_message::write_tcp_message(&mut stream, &response.encode().unwrap()).is_err()
+                {
+                    break;
+                }
+            }
+        });
+        (addr, handle)
+    }
+
+    #[test]
+    fn test_get_zone_records_via_axfr() {
+        let (addr, handle) = axfr_server(vec![
+            (
+                vec![
+                    soa(),
+                    ResourceRecord {
+                        name: "www.example.com".to_string(),
+                        rtype: TYPE_A,
+                        class: CLASS_IN,
+                        ttl: 300,
+                        rdata: vec![192, 0, 2, 1],
+                    },
+                ],
+                Signature::Signed,
+            ),
+            (
+                vec![ResourceRecord {
+                    name: "example.com".to_string(),
+                    rtype: TYPE_AAAA,
+                    class: CLASS_IN,
+                    ttl: 300,
+                    rdata: "2001:db8::1".parse::<Ipv6Addr>().unwrap().octets().to_vec(),
+                }],
+                Signature::Unsigned,
+            ),
+            (vec![soa()], Signature::Signed),
+        ]);
+        let records = backend(addr, SECRET).get_zone_records().unwrap();
+        handle.join().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "www");
+        assert_eq!(records[0].kind, DnsRecordType::A);
+        assert_eq!(records[0].data, "192.0.2.1");
+        assert_eq!(records[1].name, "@");
+        assert_eq!(records[1].data, "2001:db8::1");
+    }
+
+    #[test]
+    fn test_axfr_checks_every_signature() {
+        let www = ResourceRecord {
+            name: "www.example.com".to_string(),
+            rtype: TYPE_A,
+            class: CLASS_IN,
+            ttl: 300,
+            rdata: vec![192, 0, 2, 1],
+        };
+        let (addr, handle) = axfr_server(vec![
+            (vec![soa()], Signature::Signed),
+            (vec![www.clone()], Signature::Forged),
+            (vec![soa()], Signature::Signed),
+        ]);
+        let error = backend(addr, SECRET).get_zone_records().unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(error.message, "TSIG signature verification failed");
+
+        let (addr, handle) = axfr_server(vec![
+            (vec![soa(), www], Signature::Signed),
+            (vec![soa()], Signature::Unsigned),
+        ]);
+        let error = backend(addr, SECRET).get_zone_records().unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(
+            error.message,
+            "Zone transfer ended with an unsigned message"
+        );
+    }
+
+    #[test]
+    fn test_tcp_update_id_mismatch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let key = test_key();
+            let (mut stream, _) = listener.accept().unwrap();
+            let raw = dns_message::read_tcp_message(&mut stream).unwrap();
+            let mac = key.verify(&raw, None, unix_time()).unwrap();
+            let mut response = Message::decode(&raw).unwrap().response(RCODE_NOERROR);
+            response.id = response.id.wrapping_add(1);
+            key.sign(&mut response, Some(&mac), unix_time()).unwrap();
+            dns_message::write_tcp_message(&mut stream, &response.encode().unwrap()).unwrap();
+        });
+        let backend = Rfc2136Backend {
+            tcp: true,
+            ..backend(addr, SECRET)
+        };
+        let error = backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::A,
+                name: "host".to_string(),
+                data: "10.0.0.1".to_string(),
+            })
+            .unwrap_err();
+        handle.join().unwrap();
+        assert_eq!(
+            error.message,
+            format!("Response from {addr} has a mismatched ID")
+        );
+    }
+}
@@ -0,0 +1,686 @@
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use crate::providers::xml;
+use chrono::Utc;
+use reqwest::blocking::Client;
+use reqwest::Method;
+use ring::{digest, hmac};
+use serde_derive::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_ENDPOINT: &str = "https://route53.amazonaws.com";
+const API_VERSION: &str = "2013-04-01";
+// Route 53 is a global service, requests are always signed for us-east-1
+const SIGNING_REGION: &str = "us-east-1";
+const SIGNING_SERVICE: &str = "route53";
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Route53BackendConfig {
+    /// Hosted zone ID, with or without the `/hostedzone/` prefix
+    pub hosted_zone_id: String,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    /// Profile to read from the shared credentials file
+    pub profile: Option<String>,
+    /// Shared credentials file, defaults to `~/.aws/credentials`
+    pub credentials_file: Option<String>,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Resolve credentials from the config, then `AWS_*` environment variables, then the
+    /// shared credentials file. An explicitly configured profile skips the environment.
+    pub fn resolve(config: &Route53BackendConfig) -> Result<Self, DnsBackendError> {
+        if let (Some(access_key_id), Some(secret_access_key)) =
+            (&config.access_key_id, &config.secret_access_key)
+        {
+            return Ok(AwsCredentials {
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret_access_key.clone(),
+                session_token: config.session_token.clone(),
+            });
+        }
+        if config.profile.is_none() {
+            if let (Ok(access_key_id), Ok(secret_access_key)) = (
+                env::var("AWS_ACCESS_KEY_ID"),
+                env::var("AWS_SECRET_ACCESS_KEY"),
+            ) {
+                return Ok(AwsCredentials {
+                    access_key_id,
+                    secret_access_key,
+                    session_token: env::var("AWS_SESSION_TOKEN").ok(),
+                });
+            }
+        }
+        let profile = config
+            .profile
+            .clone()
+            .or_else(|| env::var("AWS_PROFILE").ok())
+            .unwrap_or_else(|| "default".to_string());
+        let path = match &config.credentials_file {
+            Some(path) => PathBuf::from(path),
+            None => match env::var("AWS_SHARED_CREDENTIALS_FILE") {
+                Ok(path) => PathBuf::from(path),
+                Err(_) => PathBuf::from(env::var("HOME").unwrap_or_default())
+                    .join(".aws")
+                    .join("credentials"),
+            },
+        };
+        let contents = fs::read_to_string(&path).map_err(|e| DnsBackendError {
+            message: format!(
+                "No AWS credentials configured and could not read {}: {e}",
+                path.display()
+            ),
+        })?;
+        Self::from_profile(&contents, &profile).ok_or(DnsBackendError {
+            message: format!(
+                "Profile {profile} in {} is missing aws_access_key_id or aws_secret_access_key",
+                path.display()
+            ),
+        })
+    }
+
+    fn from_profile(contents: &str, profile: &str) -> Option<Self> {
+        let mut in_profile = false;
+        let mut access_key_id = None;
+        let mut secret_access_key = None;
+        let mut session_token = None;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with(';') || line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                in_profile = line[1..line.len() - 1].trim() == profile;
+                continue;
+            }
+            if !in_profile {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = Some(value.trim().to_string());
+                match key.trim() {
+                    "aws_access_key_id" => access_key_id = value,
+                    "aws_secret_access_key" => secret_access_key = value,
+                    "aws_session_token" => session_token = value,
+                    _ => {}
+                }
+            }
+        }
+        Some(AwsCredentials {
+            access_key_id: access_key_id?,
+            secret_access_key: secret_access_key?,
+            session_token,
+        })
+    }
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    hex::encode(digest::digest(&digest::SHA256, data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, data.as_bytes()).as_ref().to_vec()
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters, as SigV4 requires
+pub fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// A request to be signed with AWS Signature Version 4
+pub struct SigV4Request<'a> {
+    pub method: &'a str,
+    pub host: &'a str,
+    pub path: &'a str,
+    pub query: &'a [(String, String)],
+    pub payload: &'a [u8],
+    pub amz_date: &'a str,
+}
+
+/// Compute the headers (including `Authorization`) that sign `request`
+pub fn sign_v4(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    request: &SigV4Request,
+) -> Vec<(String, String)> {
+    let date = &request.amz_date[..8];
+    let mut headers = vec![
+        ("host".to_string(), request.host.to_string()),
+        ("x-amz-date".to_string(), request.amz_date.to_string()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    let signed_headers = headers
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect::<Vec<&str>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(key, value)| format!("{key}:{}\n", value.trim()))
+        .collect();
+    let mut query: Vec<(String, String)> = request
+        .query
+        .iter()
+        .map(|(key, value)| (uri_encode(key, true), uri_encode(value, true)))
+        .collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<String>>()
+        .join("&");
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        uri_encode(request.path, false),
+        canonical_query,
+        canonical_headers,
+        signed_headers,
+        hex_sha256(request.payload)
+    );
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        request.amz_date,
+        scope,
+        hex_sha256(canonical_request.as_bytes())
+    );
+    let mut key = hmac_sha256(
+        format!("AWS4{}", credentials.secret_access_key).as_bytes(),
+        date,
+    );
+    for part in [region, service, "aws4_request"] {
+        key = hmac_sha256(&key, part);
+    }
+    let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+    headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_headers, signature
+        ),
+    ));
+    // reqwest sets the Host header itself from the URL
+    headers.retain(|(key, _)| key != "host");
+    headers
+}
+
+/// Route 53 returns names with special characters escaped as `\ddd` octal sequences
+fn unescape_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if digits.len() == 3 && digits.chars().all(|x| x.is_digit(8)) {
+                if let Ok(value) = u8::from_str_radix(&digits, 8) {
+                    out.push(value as char);
+                    chars.nth(2);
+                    continue;
+                }
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+pub struct Route53Backend {
+    client: Client,
+    credentials: AwsCredentials,
+    endpoint: String,
+    hosted_zone_id: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl Route53Backend {
+    pub fn new(config: Route53BackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let credentials = AwsCredentials::resolve(&config)?;
+        let hosted_zone_id = config
+            .hosted_zone_id
+            .trim_start_matches("/hostedzone/")
+            .to_string();
+        Ok(Self {
+            client: Client::new(),
+            credentials,
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            hosted_zone_id,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        })
+    }
+
+    fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(String, String)],
+        body: Option<String>,
+    ) -> Result<xml::Element, DnsBackendError> {
+        let mut url = reqwest::Url::parse(&format!("{}{}", self.endpoint, path)).map_err(|e| {
+            DnsBackendError {
+                message: format!("Invalid Route 53 endpoint {}: {e}", self.endpoint),
+            }
+        })?;
+        if !query.is_empty() {
+            url.set_query(Some(
+                &query
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}={}", uri_encode(key, true), uri_encode(value, true))
+                    })
+                    .collect::<Vec<String>>()
+                    .join("&"),
+            ));
+        }
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let payload = body.unwrap_or_default();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let headers = sign_v4(
+            &self.credentials,
+            SIGNING_REGION,
+            SIGNING_SERVICE,
+            &SigV4Request {
+                method: method.as_str(),
+                host: &host,
+                path,
+                query,
+                payload: payload.as_bytes(),
+                amz_date: &amz_date,
+            },
+        );
+        let mut builder = self.client.request(method, url);
+        for (key, value) in headers {
+            builder = builder.header(key, value);
+        }
+        if !payload.is_empty() {
+            builder = builder
+                .header("content-type", "application/xml")
+                .body(payload);
+        }
+        let response = builder.send().map_err(|e| DnsBackendError {
+            message: format!("Dns backend error: {e:?}"),
+        })?;
+        let status = response.status();
+        let text = response.text().map_err(|e| DnsBackendError {
+            message: format!("Dns backend error: {e:?}"),
+        })?;
+        if !status.is_success() {
+            let (code, message) = match xml::parse(&text) {
+                Ok(document) => (
+                    document.find("Code").map(|x| x.text.trim().to_string()),
+                    document.find("Message").map(|x| x.text.trim().to_string()),
+                ),
+                Err(_) => (None, None),
+            };
+            return Err(DnsBackendError {
+                message: format!(
+                    "Route 53 returned {status}: {} {}",
+                    code.unwrap_or_default(),
+                    message.unwrap_or(text)
+                ),
+            });
+        }
+        xml::parse(&text)
+    }
+
+    fn _get_record_sets_internal(&self) -> Result<Vec<xml::Element>, DnsBackendError> {
+        let path = format!("/{API_VERSION}/hostedzone/{}/rrset", self.hosted_zone_id);
+        let mut record_sets = Vec::new();
+        let mut query: Vec<(String, String)> = Vec::new();
+        loop {
+            let document = self.request(Method::GET, &path, &query, None)?;
+            if let Some(sets) = document.child("ResourceRecordSets") {
+                record_sets.extend(sets.children_named("ResourceRecordSet").cloned());
+            }
+            if document.child_text("IsTruncated") != Some("true") {
+                break;
+            }
+            query.clear();
+            for (element, param) in [
+                ("NextRecordName", "name"),
+                ("NextRecordType", "type"),
+                ("NextRecordIdentifier", "identifier"),
+            ] {
+                if let Some(value) = document.child_text(element) {
+                    query.push((param.to_string(), value.to_string()));
+                }
+            }
+        }
+        debug!(
+            "Fetched {} record sets from route 53 for zone {}",
+            record_sets.len(),
+            &self.zone
+        );
+        Ok(record_sets)
+    }
+
+    fn upsert(&self, record: &DnsRecord, data: &str) -> Result<(), DnsBackendError> {
+        let value = match record.kind {
+            DnsRecordType::A | DnsRecordType::AAAA => data.to_string(),
+            DnsRecordType::TXT => format!("\"{}\"", data.replace('"', "\\\"")),
+            _ => {
+                return Err(DnsBackendError {
+                    message: "Record type not supported".to_string(),
+                })
+            }
+        };
+        let body = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/{API_VERSION}/">
+  <ChangeBatch>
+    <Comment>dns-agent</Comment>
+    <Changes>
+      <Change>
+        <Action>UPSERT</Action>
+        <ResourceRecordSet>
+          <Name>{}.</Name>
+          <Type>{}</Type>
+          <TTL>{}</TTL>
+          <ResourceRecords>
+            <ResourceRecord>
+              <Value>{}</Value>
+            </ResourceRecord>
+          </ResourceRecords>
+        </ResourceRecordSet>
+      </Change>
+    </Changes>
+  </ChangeBatch>
+</ChangeResourceRecordSetsRequest>
+"#,
+            xml::escape(&fqdn(&record.name, &self.zone)),
+            String::from(record.kind.clone()),
+            self.ttl,
+            xml::escape(&value)
+        );
+        let path = format!("/{API_VERSION}/hostedzone/{}/rrset/", self.hosted_zone_id);
+        let document = self.request(Method::POST, &path, &[], Some(body))?;
+        if let Some(change_id) = document.find("Id") {
+            debug!("Submitted route 53 change {}", change_id.text.trim());
+        }
+        Ok(())
+    }
+}
+
+impl DnsBackend for Route53Backend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let record_sets = self._get_record_sets_internal()?;
+        let mut records = Vec::new();
+        for set in record_sets {
+            let name = relative_name(
+                &unescape_name(set.child_text("Name").unwrap_or("")),
+                &self.zone,
+            );
+            let kind: DnsRecordType = set.child_text("Type").unwrap_or("").into();
+            // Alias record sets have no ResourceRecords and can't be managed here
+            let values = match set.child("ResourceRecords") {
+                Some(values) => values,
+                None => continue,
+            };
+            for value in values.children_named("ResourceRecord") {
+                let data = value.child_text("Value").unwrap_or("");
+                let data = match kind {
+                    DnsRecordType::TXT => data.trim_matches('"').replace("\\\"", "\""),
+                    _ => data.to_string(),
+                };
+                records.push(DnsRecord {
+                    kind: kind.clone(),
+                    name: name.clone(),
+                    data,
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self.upsert(&record, &record.data)
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        self.upsert(record, new_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        }
+    }
+
+    fn backend(endpoint: &str) -> Route53Backend {
+        let config = Route53BackendConfig {
+            hosted_zone_id: "/hostedzone/Z0EXAMPLE".to_string(),
+            access_key_id: Some("AKIDEXAMPLE".to_string()),
+            secret_access_key: Some("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string()),
+            session_token: None,
+            profile: None,
+            credentials_file: None,
+            ttl: Some(300),
+            endpoint: Some(endpoint.to_string()),
+        };
+        Route53Backend::new(config, "example.com".to_string()).unwrap()
+    }
+
+    /// Recompute the signature the way Route 53 would and compare it to the request's
+    fn assert_signed(request: &test_http::Request) {
+        let (path, raw_query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        let query: Vec<(String, String)> = raw_query
+            .split('&')
+            .filter(|x| !x.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                let decoded = reqwest::Url::parse(&format!("http://x/?v={value}")).unwrap();
+                let value = decoded.query_pairs().next().unwrap().1.to_string();
+                (key.to_string(), value)
+            })
+            .collect();
+        let expected = sign_v4(
+            &credentials(),
+            SIGNING_REGION,
+            SIGNING_SERVICE,
+            &SigV4Request {
+                method: &request.method,
+                host: request.header("host").unwrap(),
+                path,
+                query: &query,
+                payload: request.body.as_bytes(),
+                amz_date: request.header("x-amz-date").unwrap(),
+            },
+        );
+        let authorization = expected
+            .iter()
+            .find(|(key, _)| key == "authorization")
+            .map(|(_, value)| value.as_str());
+        assert_eq!(request.header("authorization"), authorization);
+    }
+
+    #[test]
+    fn test_sign_v4_vanilla() {
+        // get-vanilla from the AWS Signature Version 4 test suite
+        let headers = sign_v4(
+            &credentials(),
+            "us-east-1",
+            "service",
+            &SigV4Request {
+                method: "GET",
+                host: "example.amazonaws.com",
+                path: "/",
+                query: &[],
+                payload: b"",
+                amz_date: "20150830T123600Z",
+            },
+        );
+        assert_eq!(
+            headers.last().unwrap().1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_credentials_from_profile() {
+        let contents = "[default]\naws_access_key_id = A\naws_secret_access_key = B\n\n\
+                        [dns]\naws_access_key_id=C\naws_secret_access_key=D\naws_session_token=E\n";
+        assert_eq!(
+            AwsCredentials::from_profile(contents, "dns"),
+            Some(AwsCredentials {
+                access_key_id: "C".to_string(),
+                secret_access_key: "D".to_string(),
+                session_token: Some("E".to_string()),
+            })
+        );
+        assert_eq!(AwsCredentials::from_profile(contents, "missing"), None);
+    }
+
+    #[test]
+    fn test_get_zone_records_paginates() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.path.contains("name=") {
+                Response::new(
+                    200,
+                    r#"<?xml version="1.0"?>
+<ListResourceRecordSetsResponse xmlns="https://route53.amazonaws.com/doc/2013-04-01/">
+  <ResourceRecordSets>
+    <ResourceRecordSet><Name>\052.example.com.</Name><Type>AAAA</Type><TTL>300</TTL>
+      <ResourceRecords><ResourceRecord><Value>2001:db8::1</Value></ResourceRecord></ResourceRecords>
+    </ResourceRecordSet>
+  </ResourceRecordSets>
+  <IsTruncated>false</IsTruncated>
+  <MaxItems>1</MaxItems>
+</ListResourceRecordSetsResponse>"#,
+                )
+            } else {
+                Response::new(
+                    200,
+                    r#"<?xml version="1.0"?>
+<ListResourceRecordSetsResponse xmlns="https://route53.amazonaws.com/doc/2013-04-01/">
+  <ResourceRecordSets>
+    <ResourceRecordSet><Name>www.dev.example.com.</Name><Type>A</Type><TTL>300</TTL>
+      <ResourceRecords><ResourceRecord><Value>192.0.2.1</Value></ResourceRecord></ResourceRecords>
+    </ResourceRecordSet>
+    <ResourceRecordSet><Name>alias.example.com.</Name><Type>A</Type>
+      <AliasTarget><DNSName>lb.example.net.</DNSName></AliasTarget>
+    </ResourceRecordSet>
+  </ResourceRecordSets>
+  <IsTruncated>true</IsTruncated>
+  <NextRecordName>*.example.com.</NextRecordName>
+  <NextRecordType>AAAA</NextRecordType>
+  <MaxItems>2</MaxItems>
+</ListResourceRecordSetsResponse>"#,
+                )
+            }
+        });
+        let records = backend(&url).get_zone_records().unwrap();
+        let requests = handle.join().unwrap();
+        requests.iter().for_each(assert_signed);
+        assert_eq!(
+            requests[1].path,
+            "/2013-04-01/hostedzone/Z0EXAMPLE/rrset?name=%2A.example.com.&type=AAAA"
+        );
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "www.dev");
+        assert_eq!(records[0].data, "192.0.2.1");
+        assert_eq!(records[1].name, "*");
+        assert_eq!(records[1].kind, DnsRecordType::AAAA);
+    }
+
+    #[test]
+    fn test_update_record_upserts() {
+        let (url, handle) = test_http::serve(1, |_| {
+            Response::new(
+                200,
+                r#"<?xml version="1.0"?>
+<ChangeResourceRecordSetsResponse><ChangeInfo><Id>/change/C1</Id><Status>PENDING</Status></ChangeInfo></ChangeResourceRecordSetsResponse>"#,
+            )
+        });
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "host".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend(&url).update_record(&record, "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+        let request = &requests[0];
+        assert_signed(request);
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/2013-04-01/hostedzone/Z0EXAMPLE/rrset/");
+        let body = xml::parse(&request.body).unwrap();
+        let change = body.find("Change").unwrap();
+        assert_eq!(change.child_text("Action"), Some("UPSERT"));
+        let set = change.child("ResourceRecordSet").unwrap();
+        assert_eq!(set.child_text("Name"), Some("host.example.com."));
+        assert_eq!(set.child_text("Type"), Some("A"));
+        assert_eq!(set.child_text("TTL"), Some("300"));
+        assert_eq!(
+            set.find("Value").map(|x| x.text.as_str()),
+            Some("192.0.2.2")
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        let (url, handle) = test_http::serve(1, |_| {
+            Response::new(
+                403,
+                r#"<?xml version="1.0"?>
+<ErrorResponse><Error><Type>Sender</Type><Code>SignatureDoesNotMatch</Code><Message>bad signature</Message></Error></ErrorResponse>"#,
+            )
+        });
+        let result = backend(&url).get_zone_records();
+        handle.join().unwrap();
+        assert_eq!(
+            result.unwrap_err().message,
+            "Route 53 returned 403 Forbidden: SignatureDoesNotMatch bad signature"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::dns_providers::{apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.scaleway.com/domain/v2beta1";
+const DEFAULT_TTL: u32 = 3600;
+const PAGE_SIZE: u32 = 500;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScalewayBackendConfig {
+    /// API secret key
+    pub secret_key: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ScalewayRecord {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    data: String,
+    ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordsResponse {
+    records: Vec<ScalewayRecord>,
+    total_count: u32,
+}
+
+#[derive(Serialize, Debug)]
+struct IdFields<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum RecordChange<'a> {
+    Add {
+        records: Vec<ScalewayRecord>,
+    },
+    /// Replace every record matching `id_fields`
+    Set {
+        id_fields: IdFields<'a>,
+        records: Vec<ScalewayRecord>,
+    },
+}
+
+#[derive(Serialize, Debug)]
+struct PatchRecordsRequest<'a> {
+    changes: Vec<RecordChange<'a>>,
+    return_all_records: bool,
+}
+
+pub struct ScalewayBackend {
+    client: Client,
+    endpoint: String,
+    secret_key: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl ScalewayBackend {
+    pub fn new(config: ScalewayBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            secret_key: config.secret_key,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let response = request
+            .header("X-Auth-Token", &self.secret_key)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Scaleway API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Scaleway API response: {e:?}"),
+        })
+    }
+
+    fn records_url(&self) -> String {
+        format!("{}/dns-zones/{}/records", self.endpoint, self.zone)
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<ScalewayRecord>, DnsBackendError> {
+        let mut records = Vec::new();
+        let mut page = 1;
+        loop {
+            let request = self
+                .client
+                .get(self.records_url())
+                .query(&[("page", page), ("page_size", PAGE_SIZE)]);
+            let response: RecordsResponse = self.send(request)?;
+            let page_len = response.records.len();
+            records.extend(response.records);
+            if page_len == 0 || records.len() as u32 >= response.total_count {
+                break;
+            }
+            page += 1;
+        }
+        debug!(
+            "Fetched records from scaleway for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+
+    fn patch(&self, change: RecordChange) -> Result<(), DnsBackendError> {
+        let body = PatchRecordsRequest {
+            changes: vec![change],
+            return_all_records: false,
+        };
+        let request = self.client.patch(self.records_url()).json(&body);
+        self.send::<serde::de::IgnoredAny>(request)?;
+        Ok(())
+    }
+
+    fn scaleway_record(&self, record: &DnsRecord, data: &str) -> ScalewayRecord {
+        ScalewayRecord {
+            name: apex_as_empty(&record.name).to_string(),
+            kind: record.kind.clone().into(),
+            data: data.to_string(),
+            ttl: self.ttl,
+        }
+    }
+}
+
+impl DnsBackend for ScalewayBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            ._get_records_internal()?
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: x.kind.as_str().into(),
+                name: normalize_name(&x.name, &self.zone),
+                data: x.data,
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let change = RecordChange::Add {
+            records: vec![self.scaleway_record(&record, &record.data)],
+        };
+        self.patch(change).map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let change = RecordChange::Set {
+            id_fields: IdFields {
+                name: apex_as_empty(&record.name),
+                kind: record.kind.clone().into(),
+            },
+            records: vec![self.scaleway_record(record, new_data)],
+        };
+        self.patch(change).map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })
+    }
+
+    fn updates_whole_rrset(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns_providers::DnsRecordType;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_update_record_sets_by_name_and_type() {
+        let (url, handle) = test_http::serve(1, |_| Response::new(200, r#"{"records":[]}"#));
+        let config = ScalewayBackendConfig {
+            secret_key: "secret".to_string(),
+            ttl: Some(300),
+            endpoint: Some(url),
+        };
+        let backend = ScalewayBackend::new(config, "example.fr".to_string());
+        let record = DnsRecord {
+            kind: DnsRecordType::AAAA,
+            name: "@".to_string(),
+            data: "2001:db8::1".to_string(),
+        };
+        backend.update_record(&record, "2001:db8::2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].method, "PATCH");
+        assert_eq!(requests[0].path, "/dns-zones/example.fr/records");
+        assert_eq!(requests[0].header("x-auth-token"), Some("secret"));
+        assert_eq!(
+            requests[0].body,
+            r#"{"changes":[{"set":{"id_fields":{"name":"","type":"AAAA"},"records":[{"name":"","type":"AAAA","data":"2001:db8::2","ttl":300}]}}],"return_all_records":false}"#
+        );
+    }
+
+    #[test]
+    fn test_pages_until_total_count() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.path.contains("page=1&") {
+                Response::new(
+                    200,
+                    r#"{"records":[{"name":"","type":"A","data":"192.0.2.1","ttl":300},
+                        {"name":"www","type":"A","data":"192.0.2.2","ttl":300}],"total_count":3}"#,
+                )
+            } else {
+                Response::new(
+                    200,
+                    r#"{"records":[{"name":"www","type":"AAAA","data":"2001:db8::1","ttl":300}],"total_count":3}"#,
+                )
+            }
+        });
+        let config = ScalewayBackendConfig {
+            secret_key: "secret".to_string(),
+            ttl: None,
+            endpoint: Some(url),
+        };
+        // A third request would fail, since the server only answers two
+        let records = ScalewayBackend::new(config, "example.fr".to_string())
+            .get_zone_records()
+            .unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].name, "@");
+        assert_eq!(records[2].kind, DnsRecordType::AAAA);
+        assert_eq!(
+            requests[0].path,
+            "/dns-zones/example.fr/records?page=1&page_size=500"
+        );
+        assert_eq!(
+            requests[1].path,
+            "/dns-zones/example.fr/records?page=2&page_size=500"
+        );
+    }
+}
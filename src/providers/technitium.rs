@@ -0,0 +1,238 @@
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::Client;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TechnitiumBackendConfig {
+    /// Base URL of the DNS server's web console, e.g. `http://dns.lan:5380`
+    pub url: String,
+    /// API token created under the console's Sessions page
+    pub token: String,
+    pub ttl: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ApiResponse<T> {
+    status: String,
+    error_message: Option<String>,
+    response: Option<T>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordsResponse {
+    records: Vec<TechnitiumRecord>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RData {
+    ip_address: Option<String>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TechnitiumRecord {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    r_data: RData,
+}
+
+pub struct TechnitiumBackend {
+    client: Client,
+    url: String,
+    token: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl TechnitiumBackend {
+    pub fn new(config: TechnitiumBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            url: config.url.trim_end_matches('/').to_string(),
+            token: config.token,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    /// Call an API method; every method takes its arguments as query parameters
+    fn call<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, String)],
+    ) -> Result<Option<T>, DnsBackendError> {
+        let response = self
+            .client
+            .get(format!("{}/api/{method}", self.url))
+            .query(&[("token", &self.token), ("zone", &self.zone)])
+            .query(params)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        let text = response.text().unwrap_or_default();
+        let body: ApiResponse<T> = serde_json::from_str(&text).map_err(|_| DnsBackendError {
+            message: format!("Technitium API returned {status}: {text}"),
+        })?;
+        if body.status != "ok" {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Technitium API returned {}: {}",
+                    body.status,
+                    body.error_message.unwrap_or_default()
+                ),
+            });
+        }
+        Ok(body.response)
+    }
+
+    /// The query parameter carrying the record data for a type
+    fn data_param(kind: &DnsRecordType) -> Result<&'static str, DnsBackendError> {
+        match kind {
+            DnsRecordType::A | DnsRecordType::AAAA => Ok("ipAddress"),
+            DnsRecordType::TXT => Ok("text"),
+            _ => Err(DnsBackendError {
+                message: "Record type not supported".to_string(),
+            }),
+        }
+    }
+}
+
+impl DnsBackend for TechnitiumBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let response: RecordsResponse = self
+            .call(
+                "zones/records/get",
+                &[
+                    ("domain", self.zone.clone()),
+                    ("listZone", "true".to_string()),
+                ],
+            )?
+            .ok_or(DnsBackendError {
+                message: "Technitium API returned no records".to_string(),
+            })?;
+        debug!(
+            "Fetched records from technitium for domain {}: {:?}",
+            &self.zone, response.records
+        );
+        Ok(response
+            .records
+            .into_iter()
+            .filter_map(|x| {
+                let data = x.r_data.ip_address.or(x.r_data.text)?;
+                Some(DnsRecord {
+                    kind: x.kind.as_str().into(),
+                    name: relative_name(&x.name, &self.zone),
+                    data,
+                })
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let param = Self::data_param(&record.kind)?;
+        let kind: String = record.kind.clone().into();
+        self.call::<serde::de::IgnoredAny>(
+            "zones/records/add",
+            &[
+                ("domain", fqdn(&record.name, &self.zone)),
+                ("type", kind),
+                ("ttl", self.ttl.to_string()),
+                (param, record.data.clone()),
+            ],
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let param = Self::data_param(&record.kind)?;
+        let new_param = match param {
+            "ipAddress" => "newIpAddress",
+            _ => "newText",
+        };
+        let kind: String = record.kind.clone().into();
+        self.call::<serde::de::IgnoredAny>(
+            "zones/records/update",
+            &[
+                ("domain", fqdn(&record.name, &self.zone)),
+                ("type", kind),
+                ("ttl", self.ttl.to_string()),
+                (param, record.data.clone()),
+                (new_param, new_data.to_string()),
+            ],
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    #[test]
+    fn test_records_and_update() {
+        let (url, handle) = test_http::serve(3, |request| {
+            if request.path.starts_with("/api/zones/records/get") {
+                Response::new(
+                    200,
+                    r#"{"status":"ok","response":{"zone":{"name":"home.lan"},"records":[
+                        {"name":"home.lan","type":"SOA","ttl":900,"rData":{"primaryNameServer":"dns.home.lan"}},
+                        {"name":"nas.home.lan","type":"A","ttl":3600,"rData":{"ipAddress":"192.168.1.10"}}]}}"#,
+                )
+            } else if request.path.contains("newIpAddress") {
+                Response::new(200, r#"{"status":"ok","response":{}}"#)
+            } else {
+                Response::new(
+                    200,
+                    r#"{"status":"error","errorMessage":"Invalid token or session expired."}"#,
+                )
+            }
+        });
+        let config = TechnitiumBackendConfig {
+            url,
+            token: "abc".to_string(),
+            ttl: Some(300),
+        };
+        let backend = TechnitiumBackend::new(config, "home.lan".to_string());
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name, "nas");
+        backend.update_record(&records[0], "192.168.1.11").unwrap();
+        let error = backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::AAAA,
+                name: "nas".to_string(),
+                data: "fd00::10".to_string(),
+            })
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Failed to create DNS record nas: Technitium API returned error: Invalid token or session expired."
+        );
+        let requests = handle.join().unwrap();
+        assert_eq!(
+            requests[1].path,
+            "/api/zones/records/update?token=abc&zone=home.lan&domain=nas.home.lan&type=A&ttl=300&ipAddress=192.168.1.10&newIpAddress=192.168.1.11"
+        );
+    }
+}
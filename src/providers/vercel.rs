@@ -0,0 +1,296 @@
+use super::create_then_delete;
+use crate::dns_providers::{
+    apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.vercel.com";
+const DEFAULT_TTL: u32 = 3600;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VercelBackendConfig {
+    pub api_token: String,
+    /// Team owning the domain, when it isn't in the token owner's personal account
+    pub team_id: Option<String>,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct VercelRecord {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    value: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pagination {
+    next: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordsResponse {
+    records: Vec<VercelRecord>,
+    pagination: Option<Pagination>,
+}
+
+#[derive(Serialize, Debug)]
+struct CreateRecordRequest<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: String,
+    value: &'a str,
+    ttl: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct CreateRecordResponse {
+    uid: String,
+}
+
+pub struct VercelBackend {
+    client: Client,
+    endpoint: String,
+    api_token: String,
+    team_id: Option<String>,
+    zone: String,
+    ttl: u32,
+}
+
+impl VercelBackend {
+    pub fn new(config: VercelBackendConfig, zone: String) -> Self {
+        Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_token: config.api_token,
+            team_id: config.team_id,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        }
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        let mut request = request.bearer_auth(&self.api_token);
+        if let Some(team_id) = &self.team_id {
+            request = request.query(&[("teamId", team_id)]);
+        }
+        let response = request.send().map_err(|e| DnsBackendError {
+            message: format!("Dns backend error: {e:?}"),
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Vercel API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        response.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Vercel API response: {e:?}"),
+        })
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<VercelRecord>, DnsBackendError> {
+        let mut records = Vec::new();
+        let mut until: Option<u64> = None;
+        loop {
+            let mut request = self
+                .client
+                .get(format!(
+                    "{}/v4/domains/{}/records",
+                    self.endpoint, self.zone
+                ))
+                .query(&[("limit", 100)]);
+            if let Some(until) = until {
+                request = request.query(&[("until", until)]);
+            }
+            let response: RecordsResponse = self.send(request)?;
+            records.extend(response.records);
+            until = response.pagination.and_then(|x| x.next);
+            if until.is_none() {
+                break;
+            }
+        }
+        debug!(
+            "Fetched records from vercel for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+
+    fn _create_record_internal(
+        &self,
+        record: &DnsRecord,
+        data: &str,
+    ) -> Result<String, DnsBackendError> {
+        let body = CreateRecordRequest {
+            name: apex_as_empty(&record.name),
+            kind: record.kind.clone().into(),
+            value: data,
+            ttl: self.ttl,
+        };
+        let request = self
+            .client
+            .post(format!(
+                "{}/v2/domains/{}/records",
+                self.endpoint, self.zone
+            ))
+            .json(&body);
+        let response: CreateRecordResponse = self.send(request)?;
+        Ok(response.uid)
+    }
+
+    fn _delete_record_internal(&self, id: &str) -> Result<(), DnsBackendError> {
+        let request = self.client.delete(format!(
+            "{}/v2/domains/{}/records/{id}",
+            self.endpoint, self.zone
+        ));
+        self.send::<serde::de::IgnoredAny>(request)?;
+        Ok(())
+    }
+}
+
+impl DnsBackend for VercelBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        Ok(self
+            ._get_records_internal()?
+            .into_iter()
+            .map(|x| DnsRecord {
+                kind: x.kind.as_str().into(),
+                name: normalize_name(&x.name, &self.zone),
+                data: x.value,
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        self._create_record_internal(&record, &record.data)
+            .map_err(|e| DnsBackendError {
+                message: format!("Failed to create DNS record {}: {}", record.name, e),
+            })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let existing_record = self
+            ._get_records_internal()?
+            .into_iter()
+            .find(|x| {
+                x.name == apex_as_empty(&record.name)
+                    && DnsRecordType::from(x.kind.as_str()) == record.kind
+                    && x.value == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        // Vercel records can't be edited, only replaced
+        create_then_delete(
+            || self._create_record_internal(record, new_data),
+            |id| self._delete_record_internal(id),
+            &existing_record.id,
+        )
+        .map_err(|e| DnsBackendError {
+            message: format!("Failed to update DNS record {record:?} with value {new_data}: {e}"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn backend(url: &str) -> VercelBackend {
+        let config = VercelBackendConfig {
+            api_token: "token".to_string(),
+            team_id: Some("team_1".to_string()),
+            ttl: None,
+            endpoint: Some(url.to_string()),
+        };
+        VercelBackend::new(config, "example.com".to_string())
+    }
+
+    #[test]
+    fn test_until_pagination() {
+        let (url, handle) = test_http::serve(2, |request| {
+            if request.path.contains("until=") {
+                Response::new(
+                    200,
+                    r#"{"records":[{"id":"rec_2","name":"www","type":"AAAA","value":"2001:db8::1"}],"pagination":{"count":1,"next":null,"prev":1700000000000}}"#,
+                )
+            } else {
+                Response::new(
+                    200,
+                    r#"{"records":[{"id":"rec_1","name":"","type":"A","value":"192.0.2.1"}],"pagination":{"count":1,"next":1700000000000,"prev":null}}"#,
+                )
+            }
+        });
+        let records = backend(&url).get_zone_records().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "@");
+        assert_eq!(records[1].kind, DnsRecordType::AAAA);
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer token"));
+        assert_eq!(
+            requests[0].path,
+            "/v4/domains/example.com/records?limit=100&teamId=team_1"
+        );
+        assert_eq!(
+            requests[1].path,
+            "/v4/domains/example.com/records?limit=100&until=1700000000000&teamId=team_1"
+        );
+    }
+
+    #[test]
+    fn test_update_rolls_back_when_delete_fails() {
+        let (url, handle) = test_http::serve(4, |request| {
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", _) => Response::new(
+                    200,
+                    r#"{"records":[{"id":"rec_old","name":"www","type":"A","value":"192.0.2.1"}],"pagination":{"count":1,"next":null,"prev":null}}"#,
+                ),
+                ("POST", _) => Response::new(200, r#"{"uid":"rec_new","updated":1}"#),
+                ("DELETE", x) if x.contains("rec_old") => {
+                    Response::new(500, r#"{"error":{"code":"internal"}}"#)
+                }
+                _ => Response::new(200, "{}"),
+            }
+        });
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        let error = backend(&url)
+            .update_record(&record, "192.0.2.2")
+            .unwrap_err();
+        assert!(error.message.contains("rolled back"), "{}", error.message);
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[1].method, "POST");
+        assert_eq!(
+            requests[1].path,
+            "/v2/domains/example.com/records?teamId=team_1"
+        );
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(body["name"], "www");
+        assert_eq!(body["value"], "192.0.2.2");
+        assert_eq!(
+            requests[3].path,
+            "/v2/domains/example.com/records/rec_new?teamId=team_1"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use crate::dns_providers::{
+    apex_as_empty, normalize_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+
+const DEFAULT_ENDPOINT: &str = "https://api.vultr.com/v2";
+const DEFAULT_TTL: u32 = 3600;
+const PER_PAGE: u32 = 500;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VultrBackendConfig {
+    pub api_key: String,
+    pub ttl: Option<u32>,
+    /// Override the API endpoint, mainly useful for testing
+    pub endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Domain {
+    domain: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct DomainResponse {
+    domain: Domain,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct VultrRecord {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    name: String,
+    data: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Links {
+    next: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Meta {
+    links: Links,
+}
+
+#[derive(Deserialize, Debug)]
+struct RecordsResponse {
+    records: Vec<VultrRecord>,
+    meta: Meta,
+}
+
+#[derive(Serialize, Debug)]
+struct CreateRecordRequest<'a> {
+    #[serde(rename = "type")]
+    kind: String,
+    name: &'a str,
+    data: &'a str,
+    ttl: u32,
+}
+
+#[derive(Serialize, Debug)]
+struct UpdateRecordRequest<'a> {
+    data: &'a str,
+    ttl: u32,
+}
+
+impl VultrRecord {
+    fn into_record(self, zone: &str) -> DnsRecord {
+        DnsRecord {
+            kind: self.kind.as_str().into(),
+            name: normalize_name(&self.name, zone),
+            data: self.data,
+        }
+    }
+}
+
+pub struct VultrBackend {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    zone: String,
+    ttl: u32,
+}
+
+impl VultrBackend {
+    /// Create the backend, checking that `zone` exists in the Vultr account.
+    /// Vultr identifies domains by their name, so that is the ID used afterwards.
+    pub fn new(config: VultrBackendConfig, zone: String) -> Result<Self, DnsBackendError> {
+        let backend = Self {
+            client: Client::new(),
+            endpoint: config
+                .endpoint
+                .unwrap_or_else(|| DEFAULT_ENDPOINT.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: config.api_key,
+            zone: zone.trim_end_matches('.').to_string(),
+            ttl: config.ttl.unwrap_or(DEFAULT_TTL),
+        };
+        let request = backend
+            .client
+            .get(format!("{}/domains/{}", backend.endpoint, backend.zone));
+        let response: DomainResponse = backend.send(request).map_err(|e| DnsBackendError {
+            message: format!("Domain {} not found in Vultr account: {}", backend.zone, e),
+        })?;
+        debug!("Resolved vultr domain {}", response.domain.domain);
+        Ok(backend)
+    }
+
+    fn send_raw(&self, request: RequestBuilder) -> Result<Response, DnsBackendError> {
+        let response = request
+            .bearer_auth(&self.api_key)
+            .send()
+            .map_err(|e| DnsBackendError {
+                message: format!("Dns backend error: {e:?}"),
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(DnsBackendError {
+                message: format!(
+                    "Vultr API returned {status}: {}",
+                    response.text().unwrap_or_default()
+                ),
+            });
+        }
+        Ok(response)
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DnsBackendError> {
+        self.send_raw(request)?.json().map_err(|e| DnsBackendError {
+            message: format!("Could not parse Vultr API response: {e:?}"),
+        })
+    }
+
+    fn _get_records_internal(&self) -> Result<Vec<VultrRecord>, DnsBackendError> {
+        let mut records = Vec::new();
+        let mut cursor = String::new();
+        loop {
+            let mut request = self
+                .client
+                .get(format!("{}/domains/{}/records", self.endpoint, self.zone))
+                .query(&[("per_page", PER_PAGE)]);
+            if !cursor.is_empty() {
+                request = request.query(&[("cursor", &cursor)]);
+            }
+            let response: RecordsResponse = self.send(request)?;
+            records.extend(response.records);
+            cursor = response.meta.links.next;
+            if cursor.is_empty() {
+                break;
+            }
+        }
+        debug!(
+            "Fetched records from vultr for domain {}: {:?}",
+            &self.zone, records
+        );
+        Ok(records)
+    }
+}
+
+impl DnsBackend for VultrBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let records = self._get_records_internal()?;
+        Ok(records
+            .into_iter()
+            .map(|x| x.into_record(&self.zone))
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let body = CreateRecordRequest {
+            kind: record.kind.clone().into(),
+            name: apex_as_empty(&record.name),
+            data: &record.data,
+            ttl: self.ttl,
+        };
+        let request = self
+            .client
+            .post(format!("{}/domains/{}/records", self.endpoint, self.zone))
+            .json(&body);
+        self.send_raw(request).map_err(|e| DnsBackendError {
+            message: format!("Failed to create DNS record {}: {}", record.name, e),
+        })?;
+        Ok(())
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let current_records = self._get_records_internal()?;
+        let existing_record = current_records
+            .into_iter()
+            .find(|x| {
+                x.name == apex_as_empty(&record.name)
+                    && DnsRecordType::from(x.kind.as_str()) == record.kind
+                    && x.data == record.data
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let body = UpdateRecordRequest {
+            data: new_data,
+            ttl: self.ttl,
+        };
+        // PATCH answers 204 No Content
+        let request = self
+            .client
+            .patch(format!(
+                "{}/domains/{}/records/{}",
+                self.endpoint, self.zone, existing_record.id
+            ))
+            .json(&body);
+        self.send_raw(request).map_err(|e| DnsBackendError {
+            message: format!(
+                "Failed to update DNS record {existing_record:?} with value {new_data}: {e}"
+            ),
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_http::{self, Response};
+
+    fn backend(url: &str) -> VultrBackend {
+        let config = VultrBackendConfig {
+            api_key: "key".to_string(),
+            ttl: None,
+            endpoint: Some(url.to_string()),
+        };
+        VultrBackend::new(config, "example.com.".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_cursor_pages_and_update() {
+        let (url, handle) = test_http::serve(4, |request| match request.path.as_str() {
+            "/domains/example.com" => Response::new(
+                200,
+                r#"{"domain":{"domain":"example.com","date_created":"2024-01-01T00:00:00+00:00"}}"#,
+            ),
+            "/domains/example.com/records?per_page=500" => Response::new(
+                200,
+                r#"{"records":[{"id":"r1","type":"A","name":"","data":"192.0.2.1"}],"meta":{"total":2,"links":{"next":"bmV4dA==","prev":""}}}"#,
+            ),
+            "/domains/example.com/records?per_page=500&cursor=bmV4dA%3D%3D" => Response::new(
+                200,
+                r#"{"records":[{"id":"r2","type":"A","name":"www","data":"192.0.2.1"}],"meta":{"total":2,"links":{"next":"","prev":""}}}"#,
+            ),
+            _ => Response::new(204, ""),
+        });
+        let backend = backend(&url);
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "www".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend.update_record(&record, "192.0.2.2").unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[0].header("Authorization"), Some("Bearer key"));
+        assert_eq!(requests[3].method, "PATCH");
+        assert_eq!(requests[3].path, "/domains/example.com/records/r2");
+        let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+        assert_eq!(body["data"], "192.0.2.2");
+    }
+
+    #[test]
+    fn test_apex_records() {
+        let (url, handle) = test_http::serve(3, |request| match request.path.as_str() {
+            "/domains/example.com" => Response::new(200, r#"{"domain":{"domain":"example.com"}}"#),
+            x if x.starts_with("/domains/example.com/records?") => Response::new(
+                200,
+                r#"{"records":[{"id":"r1","type":"A","name":"","data":"192.0.2.1"}],"meta":{"total":1,"links":{"next":"","prev":""}}}"#,
+            ),
+            _ => Response::new(201, "{}"),
+        });
+        let backend = backend(&url);
+        let records = backend.get_zone_records().unwrap();
+        assert_eq!(records[0].name, "@");
+        backend
+            .create_record(DnsRecord {
+                kind: DnsRecordType::AAAA,
+                name: "@".to_string(),
+                data: "2001:db8::1".to_string(),
+            })
+            .unwrap();
+        let requests = handle.join().unwrap();
+        assert_eq!(requests[2].method, "POST");
+        let body: serde_json::Value = serde_json::from_str(&requests[2].body).unwrap();
+        assert_eq!(body["name"], "");
+    }
+}
@@ -0,0 +1,290 @@
+//! Minimal XML reader and writer helpers for the providers whose APIs speak XML and
+//! for UPnP discovery. Only what those use is supported: elements, attributes, text, CDATA,
+//! comments and the predefined/numeric entities. Namespace prefixes are ignored.
+
+use crate::dns_providers::DnsBackendError;
+
+/// Deepest element nesting accepted, so hostile documents can't overflow the stack
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|x| x.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |x| x.name == name)
+    }
+
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|x| x.text.trim())
+    }
+
+    /// Depth-first search for the first descendant (or self) with the given name
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|x| x.find(name))
+    }
+}
+
+fn parse_error(message: &str) -> DnsBackendError {
+    DnsBackendError {
+        message: format!("Could not parse XML response: {message}"),
+    }
+}
+
+pub fn parse(input: &str) -> Result<Element, DnsBackendError> {
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_misc();
+    let root = parser.element(0)?;
+    Ok(root)
+}
+
+/// Escape text for use in element content or attribute values
+pub fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn local_name(name: &str) -> String {
+    match name.rsplit_once(':') {
+        Some((_, local)) => local.to_string(),
+        None => name.to_string(),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.input.len() - trimmed.len();
+    }
+
+    fn skip_past(&mut self, terminator: &str) -> Result<&'a str, DnsBackendError> {
+        let rest = self.rest();
+        let end = rest
+            .find(terminator)
+            .ok_or_else(|| parse_error(&format!("missing {terminator}")))?;
+        self.pos += end + terminator.len();
+        Ok(&rest[..end])
+    }
+
+    /// Skip the XML declaration, processing instructions, comments and doctype
+    fn skip_misc(&mut self) {
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            let terminator = if rest.starts_with("<?") {
+                "?>"
+            } else if rest.starts_with("<!--") {
+                "-->"
+            } else if rest.starts_with("<!") {
+                ">"
+            } else {
+                return;
+            };
+            if self.skip_past(terminator).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn name(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+            .unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn element(&mut self, depth: usize) -> Result<Element, DnsBackendError> {
+        if depth >= MAX_DEPTH {
+            return Err(parse_error("elements nested too deeply"));
+        }
+        if !self.rest().starts_with('<') {
+            return Err(parse_error("expected element"));
+        }
+        self.pos += 1;
+        let raw_name = self.name();
+        let mut element = Element {
+            name: local_name(raw_name),
+            ..Default::default()
+        };
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            } else if rest.starts_with('>') {
+                self.pos += 1;
+                break;
+            } else if rest.is_empty() {
+                return Err(parse_error("unterminated tag"));
+            }
+            let key = self.name().to_string();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(parse_error(&format!("attribute {key} has no value")));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(q) if q == '"' || q == '\'' => q,
+                _ => return Err(parse_error("unquoted attribute value")),
+            };
+            self.pos += 1;
+            let value = self.skip_past(&quote.to_string())?;
+            element.attributes.push((local_name(&key), unescape(value)));
+        }
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let close = self.skip_past(">")?;
+                if close.trim() != raw_name {
+                    return Err(parse_error(&format!(
+                        "expected </{raw_name}> but found </{}>",
+                        close.trim()
+                    )));
+                }
+                return Ok(element);
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let data = self.skip_past("]]>")?;
+                element.text.push_str(data);
+            } else if rest.starts_with('<') {
+                let child = self.element(depth + 1)?;
+                element.children.push(child);
+            } else if rest.is_empty() {
+                return Err(parse_error(&format!("unterminated element {raw_name}")));
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                element.text.push_str(&unescape(&rest[..end]));
+                self.pos += end;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_document() {
+        let doc = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- comment -->
+            <ns:Root xmlns:ns="urn:test" a='1'>
+                <Item Name="@" Address="10.0.0.1"/>
+                <Item Name="www">text &amp; more<![CDATA[<raw>]]></Item>
+            </ns:Root>"#,
+        )
+        .unwrap();
+        assert_eq!(doc.name, "Root");
+        assert_eq!(doc.attr("a"), Some("1"));
+        let items: Vec<&Element> = doc.children_named("Item").collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].attr("address"), Some("10.0.0.1"));
+        assert_eq!(items[1].text, "text & more<raw>");
+    }
+
+    #[test]
+    fn test_parse_mismatched_tag() {
+        assert!(parse("<a><b></a></b>").is_err());
+    }
+
+    #[test]
+    fn test_parse_depth_limit() {
+        let nested = |depth: usize| format!("{}{}", "<a>".repeat(depth), "</a>".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        let error = parse(&nested(100_000)).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Could not parse XML response: elements nested too deeply"
+        );
+    }
+
+    #[test]
+    fn test_escape_roundtrip() {
+        let value = r#"v=spf1 "quoted" <tag> & more"#;
+        assert_eq!(unescape(&escape(value)), value);
+    }
+}
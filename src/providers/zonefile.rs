@@ -0,0 +1,521 @@
+use super::{run_reload_command, write_atomic};
+use crate::dns_providers::{
+    fqdn, relative_name, DnsBackend, DnsBackendError, DnsRecord, DnsRecordType,
+};
+use chrono::Utc;
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialPolicy {
+    /// Add one to the current serial
+    Increment,
+    /// `YYYYMMDDnn`, falling back to incrementing if the serial is already ahead of today
+    Date,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ZoneFileBackendConfig {
+    /// Path to the master file for the zone
+    pub path: String,
+    /// Defaults to `increment`
+    pub serial: Option<SerialPolicy>,
+    /// Command (and arguments) run after the file is rewritten, e.g. `["rndc", "reload", "example.com"]`
+    pub reload_command: Option<Vec<String>>,
+    /// TTL written on new records, otherwise they inherit `$TTL`
+    pub ttl: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    text: String,
+    start: usize,
+    end: usize,
+}
+
+/// One logical line of the master file, with parenthesised continuations joined
+#[derive(Debug)]
+struct Entry {
+    tokens: Vec<Token>,
+    has_owner: bool,
+}
+
+#[derive(Debug)]
+struct MasterRecord {
+    owner: String,
+    kind: String,
+    rdata: Vec<Token>,
+}
+
+struct ParsedZone {
+    records: Vec<MasterRecord>,
+    /// `$ORIGIN` in effect at the end of the file, where new records get appended
+    final_origin: String,
+}
+
+/// Length of the escape sequence starting with the backslash at `pos`, which includes
+/// all bytes of the escaped character so tokens never end inside a UTF-8 sequence
+fn escape_len(contents: &str, pos: usize) -> usize {
+    1 + contents[pos + 1..].chars().next().map_or(0, char::len_utf8)
+}
+
+fn tokenize(contents: &str) -> Vec<Entry> {
+    let bytes = contents.as_bytes();
+    let mut entries = Vec::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut has_owner = false;
+    let mut line_start = 0;
+    let mut depth: i32 = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b';' => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b'\n' => {
+                if depth == 0 && !tokens.is_empty() {
+                    entries.push(Entry {
+                        tokens: std::mem::take(&mut tokens),
+                        has_owner,
+                    });
+                }
+                line_start = pos + 1;
+            }
+            b'(' => depth += 1,
+            b')' => depth = (depth - 1).max(0),
+            b' ' | b'\t' | b'\r' => {}
+            _ => {
+                let start = pos;
+                if bytes[pos] == b'"' {
+                    pos += 1;
+                    while pos < bytes.len() && bytes[pos] != b'"' {
+                        pos += if bytes[pos] == b'\\' {
+                            escape_len(contents, pos)
+                        } else {
+                            1
+                        };
+                    }
+                    pos = (pos + 1).min(bytes.len());
+                } else {
+                    while pos < bytes.len()
+                        && !matches!(
+                            bytes[pos],
+                            b' ' | b'\t' | b'\r' | b'\n' | b';' | b'(' | b')' | b'"'
+                        )
+                    {
+                        pos += if bytes[pos] == b'\\' {
+                            escape_len(contents, pos)
+                        } else {
+                            1
+                        };
+                    }
+                    pos = pos.min(bytes.len());
+                }
+                if tokens.is_empty() {
+                    has_owner = start == line_start;
+                }
+                tokens.push(Token {
+                    text: contents[start..pos].to_string(),
+                    start,
+                    end: pos,
+                });
+                continue;
+            }
+        }
+        pos += 1;
+    }
+    if !tokens.is_empty() {
+        entries.push(Entry { tokens, has_owner });
+    }
+    entries
+}
+
+fn resolve_name(name: &str, origin: &str) -> String {
+    if name == "@" {
+        origin.to_string()
+    } else if let Some(absolute) = name.strip_suffix('.') {
+        absolute.to_string()
+    } else {
+        fqdn(name, origin)
+    }
+}
+
+fn is_class(token: &str) -> bool {
+    matches!(
+        token.to_ascii_uppercase().as_str(),
+        "IN" | "CH" | "HS" | "CS"
+    )
+}
+
+fn parse_zone(contents: &str, zone: &str) -> ParsedZone {
+    let mut origin = zone.trim_end_matches('.').to_string();
+    let mut last_owner = origin.clone();
+    let mut records = Vec::new();
+    for entry in tokenize(contents) {
+        let first = &entry.tokens[0].text;
+        if first.starts_with('$') {
+            match first.to_ascii_uppercase().as_str() {
+                "$ORIGIN" => {
+                    if let Some(name) = entry.tokens.get(1) {
+                        origin = resolve_name(&name.text, &origin);
+                    }
+                }
+                "$TTL" => {}
+                other => warn!("Ignoring unsupported {} directive in zone file", other),
+            }
+            continue;
+        }
+        let mut index = 0;
+        let owner = if entry.has_owner {
+            index = 1;
+            resolve_name(first, &origin)
+        } else {
+            last_owner.clone()
+        };
+        last_owner = owner.clone();
+        // Optional TTL and class, in either order
+        for _ in 0..2 {
+            match entry.tokens.get(index) {
+                Some(token)
+                    if is_class(&token.text)
+                        || token.text.starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    index += 1
+                }
+                _ => break,
+            }
+        }
+        let kind = match entry.tokens.get(index) {
+            Some(token) => token.text.to_ascii_uppercase(),
+            None => continue,
+        };
+        records.push(MasterRecord {
+            owner,
+            kind,
+            rdata: entry.tokens[index + 1..].to_vec(),
+        });
+    }
+    ParsedZone {
+        records,
+        final_origin: origin,
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .unwrap_or(text);
+    inner.replace("\\\"", "\"").replace("\\\\", "\\")
+}
+
+fn presentation_data(kind: &DnsRecordType, data: &str) -> String {
+    match kind {
+        DnsRecordType::TXT => format!("\"{}\"", data.replace('\\', "\\\\").replace('"', "\\\"")),
+        _ => data.to_string(),
+    }
+}
+
+impl MasterRecord {
+    fn data(&self) -> String {
+        match self.kind.as_str() {
+            "TXT" => self.rdata.iter().map(|x| unquote(&x.text)).collect(),
+            _ => self
+                .rdata
+                .iter()
+                .map(|x| x.text.as_str())
+                .collect::<Vec<&str>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Compute the next SOA serial under `policy`
+pub fn next_serial(current: u32, policy: SerialPolicy, today: &str) -> u32 {
+    match policy {
+        SerialPolicy::Increment => current.wrapping_add(1).max(1),
+        SerialPolicy::Date => {
+            let base = today.parse::<u32>().unwrap_or(0).saturating_mul(100);
+            if current < base {
+                base
+            } else {
+                current.wrapping_add(1)
+            }
+        }
+    }
+}
+
+pub struct ZoneFileBackend {
+    path: PathBuf,
+    zone: String,
+    serial_policy: SerialPolicy,
+    reload_command: Option<Vec<String>>,
+    ttl: Option<u32>,
+}
+
+impl ZoneFileBackend {
+    pub fn new(config: ZoneFileBackendConfig, zone: String) -> Self {
+        Self {
+            path: PathBuf::from(config.path),
+            zone: zone.trim_end_matches('.').to_string(),
+            serial_policy: config.serial.unwrap_or(SerialPolicy::Increment),
+            reload_command: config.reload_command,
+            ttl: config.ttl,
+        }
+    }
+
+    fn read(&self) -> Result<String, DnsBackendError> {
+        fs::read_to_string(&self.path).map_err(|e| DnsBackendError {
+            message: format!("Failed to read zone file {}: {}", self.path.display(), e),
+        })
+    }
+
+    /// Bump the SOA serial in `contents`, which must already contain every other edit
+    fn bump_serial(&self, contents: &mut String) -> Result<(), DnsBackendError> {
+        let parsed = parse_zone(contents, &self.zone);
+        let serial_token = parsed
+            .records
+            .iter()
+            .find(|x| x.kind == "SOA")
+            .and_then(|x| x.rdata.get(2))
+            .ok_or(DnsBackendError {
+                message: format!("No SOA record found in {}", self.path.display()),
+            })?;
+        let current: u32 = serial_token.text.parse().map_err(|_| DnsBackendError {
+            message: format!("Invalid SOA serial {}", serial_token.text),
+        })?;
+        let today = Utc::now().format("%Y%m%d").to_string();
+        let serial = next_serial(current, self.serial_policy, &today);
+        debug!(
+            "Bumping serial of {} from {} to {}",
+            self.zone, current, serial
+        );
+        contents.replace_range(serial_token.start..serial_token.end, &serial.to_string());
+        Ok(())
+    }
+
+    fn write(&self, mut contents: String) -> Result<(), DnsBackendError> {
+        self.bump_serial(&mut contents)?;
+        write_atomic(&self.path, &contents).map_err(|e| DnsBackendError {
+            message: format!("Failed to write zone file {}: {}", self.path.display(), e),
+        })?;
+        if let Some(command) = &self.reload_command {
+            run_reload_command(command)?;
+        }
+        Ok(())
+    }
+}
+
+impl DnsBackend for ZoneFileBackend {
+    fn zone(&self) -> String {
+        self.zone.clone()
+    }
+
+    fn get_zone_records(&self) -> Result<Vec<DnsRecord>, DnsBackendError> {
+        let contents = self.read()?;
+        Ok(parse_zone(&contents, &self.zone)
+            .records
+            .iter()
+            .map(|x| DnsRecord {
+                kind: x.kind.as_str().into(),
+                name: relative_name(&x.owner, &self.zone),
+                data: x.data(),
+            })
+            .collect())
+    }
+
+    fn create_record(&self, record: DnsRecord) -> Result<(), DnsBackendError> {
+        let mut contents = self.read()?;
+        let parsed = parse_zone(&contents, &self.zone);
+        let owner = fqdn(&record.name, &self.zone);
+        let owner = match relative_name(&owner, &parsed.final_origin) {
+            name if name == owner => format!("{owner}."),
+            name => name,
+        };
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        let ttl = self.ttl.map(|x| format!("{x}\t")).unwrap_or_default();
+        let kind: String = record.kind.clone().into();
+        contents.push_str(&format!(
+            "{owner}\t{ttl}IN\t{kind}\t{}\n",
+            presentation_data(&record.kind, &record.data)
+        ));
+        self.write(contents)
+    }
+
+    fn update_record(&self, record: &DnsRecord, new_data: &str) -> Result<(), DnsBackendError> {
+        let mut contents = self.read()?;
+        let parsed = parse_zone(&contents, &self.zone);
+        let kind: String = record.kind.clone().into();
+        let existing = parsed
+            .records
+            .iter()
+            .find(|x| {
+                relative_name(&x.owner, &self.zone) == record.name
+                    && x.kind == kind
+                    && x.data() == record.data
+                    && !x.rdata.is_empty()
+            })
+            .ok_or(DnsBackendError {
+                message: "Tried to update a nonexistant record".to_string(),
+            })?;
+        let start = existing.rdata[0].start;
+        let end = existing.rdata[existing.rdata.len() - 1].end;
+        contents.replace_range(start..end, &presentation_data(&record.kind, new_data));
+        self.write(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZONE: &str = "$TTL 3600
+$ORIGIN example.com.
+@   IN  SOA ns1 hostmaster (
+        2024010101 ; serial
+        7200 3600 1209600 300 )
+    IN  NS  ns1
+ns1 IN  A   192.0.2.53 ; nameserver
+host    300 IN A 192.0.2.10
+        IN  AAAA 2001:db8::10
+txt IN TXT \"hello \\\"world\\\"\"
+$ORIGIN site1.example.com.
+www A 192.0.2.20
+";
+
+    fn backend(name: &str, contents: &str) -> (ZoneFileBackend, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("dns-agent-zone-{}-{name}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("example.com.zone");
+        fs::write(&path, contents).unwrap();
+        let config = ZoneFileBackendConfig {
+            path: path.to_string_lossy().to_string(),
+            serial: None,
+            reload_command: None,
+            ttl: Some(600),
+        };
+        (
+            ZoneFileBackend::new(config, "example.com".to_string()),
+            path,
+        )
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let records: Vec<(String, String, String)> = parse_zone(ZONE, "example.com")
+            .records
+            .iter()
+            .map(|x| (x.owner.clone(), x.kind.clone(), x.data()))
+            .collect();
+        assert_eq!(records[0].1, "SOA");
+        assert_eq!(
+            records[1..],
+            [
+                (
+                    "example.com".to_string(),
+                    "NS".to_string(),
+                    "ns1".to_string()
+                ),
+                (
+                    "ns1.example.com".to_string(),
+                    "A".to_string(),
+                    "192.0.2.53".to_string()
+                ),
+                (
+                    "host.example.com".to_string(),
+                    "A".to_string(),
+                    "192.0.2.10".to_string()
+                ),
+                (
+                    "host.example.com".to_string(),
+                    "AAAA".to_string(),
+                    "2001:db8::10".to_string()
+                ),
+                (
+                    "txt.example.com".to_string(),
+                    "TXT".to_string(),
+                    "hello \"world\"".to_string()
+                ),
+                (
+                    "www.site1.example.com".to_string(),
+                    "A".to_string(),
+                    "192.0.2.20".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_escaped_multibyte() {
+        let entries = tokenize("caf\\é IN TXT \"\\é\\€\" ; comment\n");
+        let tokens: Vec<&str> = entries[0].tokens.iter().map(|x| x.text.as_str()).collect();
+        assert_eq!(tokens, ["caf\\é", "IN", "TXT", "\"\\é\\€\""]);
+        assert_eq!(entries[0].tokens[3].end, "caf\\é IN TXT \"\\é\\€\"".len());
+    }
+
+    #[test]
+    fn test_update_preserves_layout() {
+        let (backend, path) = backend("update", ZONE);
+        let record = DnsRecord {
+            kind: DnsRecordType::AAAA,
+            name: "host".to_string(),
+            data: "2001:db8::10".to_string(),
+        };
+        backend.update_record(&record, "2001:db8::11").unwrap();
+        let expected = ZONE
+            .replace("2024010101", "2024010102")
+            .replace("2001:db8::10", "2001:db8::11");
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_create_appends_relative_to_final_origin() {
+        let (backend, path) = backend("create", ZONE);
+        let record = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "host.site1".to_string(),
+            data: "192.0.2.30".to_string(),
+        };
+        backend.create_record(record).unwrap();
+        let apex = DnsRecord {
+            kind: DnsRecordType::A,
+            name: "@".to_string(),
+            data: "192.0.2.1".to_string(),
+        };
+        backend.create_record(apex).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents
+            .ends_with("host\t600\tIN\tA\t192.0.2.30\nexample.com.\t600\tIN\tA\t192.0.2.1\n"));
+        assert!(contents.contains("2024010103 ; serial"));
+        let records = backend.get_zone_records().unwrap();
+        assert!(records
+            .iter()
+            .any(|x| x.name == "host.site1" && x.data == "192.0.2.30"));
+        assert!(records
+            .iter()
+            .any(|x| x.name == "@" && x.data == "192.0.2.1"));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_next_serial() {
+        assert_eq!(next_serial(41, SerialPolicy::Increment, "20240102"), 42);
+        assert_eq!(
+            next_serial(2024010105, SerialPolicy::Date, "20240102"),
+            2024010200
+        );
+        assert_eq!(
+            next_serial(2024010205, SerialPolicy::Date, "20240102"),
+            2024010206
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::dns_providers::{DnsBackendError, DnsRecord, DnsRecordType};
+use crate::providers::{atomic_tmp_path, write_atomic};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Local record of what dns-agent last published, for write-only backends that
+/// can't read zone records back from the provider.
+pub struct RecordCache {
+    path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    #[serde(default)]
+    records: Vec<CachedRecord>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct CachedRecord {
+    zone: String,
+    name: String,
+    kind: String,
+    data: String,
+}
+
+impl RecordCache {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn load(&self) -> CacheFile {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                warn!(
+                    "Ignoring unreadable record cache {}: {}",
+                    self.path.display(),
+                    e
+                );
+                CacheFile::default()
+            }),
+            Err(_) => CacheFile::default(),
+        }
+    }
+
+    /// Records last published for `zone`. A missing cache simply means nothing is known yet.
+    pub fn records(&self, zone: &str) -> Vec<DnsRecord> {
+        self.load()
+            .records
+            .into_iter()
+            .filter(|x| x.zone == zone)
+            .map(|x| DnsRecord {
+                kind: DnsRecordType::from(x.kind.as_str()),
+                name: x.name,
+                data: x.data,
+            })
+            .collect()
+    }
+
+    /// Make sure the cache can be written, by writing and removing the temporary file
+    /// `store` goes through. Without a working cache every run would repeat every update.
+    pub fn check_writable(&self) -> Result<(), DnsBackendError> {
+        let cache_error = |e: std::io::Error| DnsBackendError {
+            message: format!(
+                "Record cache {} is not writable: {}",
+                self.path.display(),
+                e
+            ),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(cache_error)?;
+        }
+        let tmp_path = atomic_tmp_path(&self.path);
+        fs::write(&tmp_path, "").map_err(cache_error)?;
+        fs::remove_file(&tmp_path).map_err(cache_error)
+    }
+
+    /// Remember `record` as the current value for its name and type in `zone`
+    pub fn store(&self, zone: &str, record: &DnsRecord) -> Result<(), DnsBackendError> {
+        let mut cache = self.load();
+        let kind: String = record.kind.clone().into();
+        cache
+            .records
+            .retain(|x| !(x.zone == zone && x.name == record.name && x.kind == kind));
+        cache.records.push(CachedRecord {
+            zone: zone.to_string(),
+            name: record.name.clone(),
+            kind,
+            data: record.data.clone(),
+        });
+        let cache_error = |e: std::io::Error| DnsBackendError {
+            message: format!(
+                "Failed to write record cache {}: {}",
+                self.path.display(),
+                e
+            ),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(cache_error)?;
+        }
+        let contents = toml::to_string(&cache).map_err(|e| DnsBackendError {
+            message: format!("Failed to serialize record cache: {e}"),
+        })?;
+        write_atomic(&self.path, &contents).map_err(cache_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_replaces_previous_value() {
+        let dir = std::env::temp_dir().join(format!("dns-agent-cache-{}", std::process::id()));
+        let cache = RecordCache::new(dir.join("cache.toml"));
+        let record = |data: &str| DnsRecord {
+            kind: DnsRecordType::A,
+            name: "home".to_string(),
+            data: data.to_string(),
+        };
+        assert!(cache.records("example.com").is_empty());
+        cache.store("example.com", &record("192.0.2.1")).unwrap();
+        cache.store("example.com", &record("192.0.2.2")).unwrap();
+        cache.store("example.org", &record("192.0.2.3")).unwrap();
+        let records = cache.records("example.com");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].data, "192.0.2.2");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
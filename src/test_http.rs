@@ -0,0 +1,109 @@
+//! A tiny single-threaded HTTP/1.1 server used by tests as a stand-in for
+//! provider APIs and address discovery services.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Path including the query string
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, body: &str) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Serve `count` requests on a random local port, returning the base URL and
+/// a handle that yields the received requests once they have all been answered.
+pub fn serve<F>(count: usize, mut handler: F) -> (String, JoinHandle<Vec<Request>>)
+where
+    F: FnMut(&Request) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let handle = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for _ in 0..count {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let path = parts.next().unwrap_or_default().to_string();
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.push((key.trim().to_string(), value.trim().to_string()));
+                }
+            }
+            let mut request = Request {
+                method,
+                path,
+                headers,
+                body: String::new(),
+            };
+            let length: usize = request
+                .header("content-length")
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(0);
+            let mut body = vec![0u8; length];
+            reader.read_exact(&mut body).unwrap();
+            request.body = String::from_utf8_lossy(&body).to_string();
+
+            let response = handler(&request);
+            let mut raw = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+                response.status,
+                response.body.len()
+            );
+            for (key, value) in &response.headers {
+                raw.push_str(&format!("{key}: {value}\r\n"));
+            }
+            raw.push_str("\r\n");
+            raw.push_str(&response.body);
+            let mut stream = reader.into_inner();
+            stream.write_all(raw.as_bytes()).unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    (base_url, handle)
+}
@@ -1,12 +1,40 @@
-use crate::config::{Config, ParsedDomainConfig};
+use crate::config::{Config, ParsedDomainConfig, Settings};
+use crate::discovery::dns::DnsSource;
+use crate::discovery::http::HttpSource;
+use crate::discovery::router::{nat_warning, RouterSource};
+use crate::discovery::stun::StunSource;
+use crate::discovery::{discover, AddressSource, DiscoveryPolicy, Family, DEFAULT_TIMEOUT};
 use crate::dns_providers::{update_records, DnsBackendError};
+use crate::providers::adguard_home::AdGuardHomeBackend;
+use crate::providers::azure_dns::AzureDnsBackend;
+use crate::providers::bunny::BunnyBackend;
 use crate::providers::cloudflare::CloudFlareBackend;
+use crate::providers::desec::DesecBackend;
 use crate::providers::digitalocean::DigitalOceanBackend;
+use crate::providers::dyndns2::Dyndns2Backend;
+use crate::providers::dynv6::Dynv6Backend;
+use crate::providers::gandi::GandiBackend;
+use crate::providers::gcloud_dns::GcloudDnsBackend;
+use crate::providers::hetzner::HetznerBackend;
+use crate::providers::hostsfile::HostsFileBackend;
+use crate::providers::linode::LinodeBackend;
+use crate::providers::namecheap::NamecheapBackend;
+use crate::providers::netlify::NetlifyBackend;
+use crate::providers::ovh::OvhBackend;
+use crate::providers::pihole::PiholeBackend;
+use crate::providers::porkbun::PorkbunBackend;
+use crate::providers::powerdns::PowerDnsBackend;
+use crate::providers::rfc2136::Rfc2136Backend;
+use crate::providers::route53::Route53Backend;
+use crate::providers::scaleway::ScalewayBackend;
+use crate::providers::technitium::TechnitiumBackend;
+use crate::providers::vercel::VercelBackend;
+use crate::providers::vultr::VultrBackend;
+use crate::providers::zonefile::ZoneFileBackend;
 use default_net::get_default_interface;
 use get_if_addrs::{get_if_addrs, Interface};
-use reqwest;
-use std::net::{IpAddr, Ipv4Addr};
-use std::str::FromStr;
+use std::net::IpAddr;
+use std::time::Duration;
 
 #[derive(Debug, PartialEq)]
 pub enum SystemAddress {
@@ -43,7 +71,12 @@ pub struct SystemV6Address {
 }
 
 impl SystemAddresses {
-    fn new(interfaces: Vec<Interface>, external_address: Option<Ipv4Addr>) -> Self {
+    fn new(
+        interfaces: Vec<Interface>,
+        external_address: Option<IpAddr>,
+        external_v6_address: Option<IpAddr>,
+        router_wan_address: Option<IpAddr>,
+    ) -> Self {
         let mut ipv4_addr: Vec<SystemV4Address> = interfaces
             .clone()
             .into_iter()
@@ -54,7 +87,7 @@ impl SystemAddresses {
             })
             .collect();
 
-        let ipv6_addr: Vec<SystemV6Address> = interfaces
+        let mut ipv6_addr: Vec<SystemV6Address> = interfaces
             .into_iter()
             .filter(|x| x.addr.ip().is_ipv6())
             .map(|x| SystemV6Address {
@@ -66,7 +99,21 @@ impl SystemAddresses {
         if let Some(external_ip) = external_address {
             ipv4_addr.push(SystemV4Address {
                 interface: "external".to_string(),
-                address: IpAddr::from(external_ip),
+                address: external_ip,
+            });
+        };
+
+        if let Some(external_ip) = external_v6_address {
+            ipv6_addr.push(SystemV6Address {
+                interface: "external".to_string(),
+                address: external_ip,
+            });
+        };
+
+        if let Some(router_wan_ip) = router_wan_address {
+            ipv4_addr.push(SystemV4Address {
+                interface: "router_wan".to_string(),
+                address: router_wan_ip,
             });
         };
 
@@ -84,32 +131,114 @@ pub fn update_dns(config: Config) {
 
     info!("Detected default interface as {}", default_interface.name);
 
-    let external_ipv4: Option<Ipv4Addr> = match config.settings {
-        Some(settings) => match settings.external_ipv4_check_url {
-            Some(url) => {
-                info!("Making request to {} for IPV4 address discovery", &url);
-                let req_body = reqwest::blocking::get(url).unwrap().text().unwrap();
-                info!(
-                    "Got response from external IP discovery service: {}",
-                    req_body
-                );
-                let ip_address = Ipv4Addr::from_str(&req_body)
-                    .expect("Couldn't parse IP from external discovery service");
-                info!("Discovered external IPv4 address: {:?}", ip_address);
-                Some(ip_address)
-            }
-            None => None,
-        },
-        None => None,
-    };
+    let settings = config.settings.as_ref();
+    let external_ipv4 = discover_external(settings, Family::V4);
+    let external_ipv6 = discover_external(settings, Family::V6);
+
+    let router_wan = discover_router_wan(settings);
+    if let Some(warning) = router_wan.and_then(|x| nat_warning(x, external_ipv4)) {
+        warn!("{}", warning);
+    }
 
-    let system_interfaces = SystemAddresses::new(local_interfaces, external_ipv4);
+    let system_interfaces =
+        SystemAddresses::new(local_interfaces, external_ipv4, external_ipv6, router_wan);
     info!("System IPs: {:?}", system_interfaces);
 
     for domain in config.domains {
         info!("Running for domain {}", domain.name);
         let parsed_domain = domain.parse_config(&default_interface);
-        update_domain_dns(parsed_domain, &system_interfaces).unwrap();
+        let domain_name = parsed_domain.name.clone();
+        if let Err(e) = update_domain_dns(parsed_domain, &system_interfaces) {
+            error!("Failed to update {}: {}", domain_name, e);
+        }
+    }
+}
+
+fn discovery_sources(settings: &Settings, family: Family) -> Vec<Box<dyn AddressSource>> {
+    let timeout = settings
+        .discovery_timeout
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT);
+    let (url, urls) = match family {
+        Family::V4 => (
+            &settings.external_ipv4_check_url,
+            &settings.external_ipv4_check_urls,
+        ),
+        Family::V6 => (
+            &settings.external_ipv6_check_url,
+            &settings.external_ipv6_check_urls,
+        ),
+    };
+    let http = url
+        .iter()
+        .chain(urls.iter().flatten())
+        .map(|x| Box::new(HttpSource::new(x.clone(), timeout)) as Box<dyn AddressSource>);
+    let stun = settings
+        .stun_servers
+        .iter()
+        .flatten()
+        .map(|x| Box::new(StunSource::new(x.clone(), timeout)) as Box<dyn AddressSource>);
+    let dns = settings
+        .dns_sources
+        .iter()
+        .flatten()
+        .filter(|x| x.serves(family))
+        .map(|x| Box::new(DnsSource::new(x.clone(), timeout)) as Box<dyn AddressSource>);
+    http.chain(stun).chain(dns).collect()
+}
+
+/// The address for the `external` pseudo-interface, if any sources are configured.
+/// Failures are logged rather than fatal, so records on other interfaces still update.
+fn discover_external(settings: Option<&Settings>, family: Family) -> Option<IpAddr> {
+    let settings = settings?;
+    let sources = discovery_sources(settings, family);
+    if sources.is_empty() {
+        return None;
+    }
+    let policy = settings.discovery_policy.unwrap_or_default();
+    match discover(&sources, family, policy) {
+        Ok(address) => {
+            info!("Discovered external {:?} address: {}", family, address);
+            Some(address)
+        }
+        Err(e) => {
+            error!("External {:?} address discovery failed: {}", family, e);
+            None
+        }
+    }
+}
+
+/// The address for the `router_wan` pseudo-interface, as reported by the gateway
+fn discover_router_wan(settings: Option<&Settings>) -> Option<IpAddr> {
+    let settings = settings?;
+    let timeout = settings
+        .discovery_timeout
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT);
+    let sources: Vec<Box<dyn AddressSource>> = settings
+        .router_protocols
+        .iter()
+        .flatten()
+        .map(|x| {
+            Box::new(RouterSource::new(
+                *x,
+                settings.router_gateway.clone(),
+                timeout,
+            )) as Box<dyn AddressSource>
+        })
+        .collect();
+    if sources.is_empty() {
+        return None;
+    }
+    match discover(&sources, Family::V4, DiscoveryPolicy::FirstSuccess) {
+        Ok(address) => {
+            info!("Router reported WAN address: {}", address);
+            Some(address)
+        }
+        Err(e) => {
+            error!("Router WAN address discovery failed: {}", e);
+            None
+        }
     }
 }
 
@@ -126,7 +255,127 @@ fn update_domain_dns(
 
         Ok(())
     } else if let Some(cloudflare_config) = domain.cloudflare_backend {
-        let backend = CloudFlareBackend::from(cloudflare_config);
+        let backend = CloudFlareBackend::new(cloudflare_config)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(rfc2136_config) = domain.rfc2136_backend {
+        let backend = Rfc2136Backend::new(rfc2136_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(route53_config) = domain.route53_backend {
+        let backend = Route53Backend::new(route53_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(hetzner_config) = domain.hetzner_backend {
+        let backend = HetznerBackend::new(hetzner_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(powerdns_config) = domain.powerdns_backend {
+        let backend = PowerDnsBackend::new(powerdns_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(dyndns2_config) = domain.dyndns2_backend {
+        let backend = Dyndns2Backend::new(dyndns2_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(zonefile_config) = domain.zonefile_backend {
+        let backend = ZoneFileBackend::new(zonefile_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(hostsfile_config) = domain.hostsfile_backend {
+        let backend = HostsFileBackend::new(hostsfile_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(gandi_config) = domain.gandi_backend {
+        let backend = GandiBackend::new(gandi_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(porkbun_config) = domain.porkbun_backend {
+        let backend = PorkbunBackend::new(porkbun_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(namecheap_config) = domain.namecheap_backend {
+        let backend = NamecheapBackend::new(namecheap_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(linode_config) = domain.linode_backend {
+        let backend = LinodeBackend::new(linode_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(vultr_config) = domain.vultr_backend {
+        let backend = VultrBackend::new(vultr_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(desec_config) = domain.desec_backend {
+        let backend = DesecBackend::new(desec_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(dynv6_config) = domain.dynv6_backend {
+        let backend = Dynv6Backend::new(dynv6_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(gcloud_dns_config) = domain.gcloud_dns_backend {
+        let backend = GcloudDnsBackend::new(gcloud_dns_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(azure_dns_config) = domain.azure_dns_backend {
+        let backend = AzureDnsBackend::new(azure_dns_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(ovh_config) = domain.ovh_backend {
+        let backend = OvhBackend::new(ovh_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(scaleway_config) = domain.scaleway_backend {
+        let backend = ScalewayBackend::new(scaleway_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(technitium_config) = domain.technitium_backend {
+        let backend = TechnitiumBackend::new(technitium_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(pihole_config) = domain.pihole_backend {
+        let backend = PiholeBackend::new(pihole_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(adguard_home_config) = domain.adguard_home_backend {
+        let backend = AdGuardHomeBackend::new(adguard_home_config, domain.name);
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(bunny_config) = domain.bunny_backend {
+        let backend = BunnyBackend::new(bunny_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(netlify_config) = domain.netlify_backend {
+        let backend = NetlifyBackend::new(netlify_config, domain.name)?;
+        update_records(backend, desired_records, system_interfaces)?;
+
+        Ok(())
+    } else if let Some(vercel_config) = domain.vercel_backend {
+        let backend = VercelBackend::new(vercel_config, domain.name);
         update_records(backend, desired_records, system_interfaces)?;
 
         Ok(())